@@ -1,5 +1,3 @@
-use std::sync::atomic::Ordering;
-
 use crossbeam_channel::{bounded, Receiver, Sender, TrySendError};
 use rand::{distributions::Standard, prelude::Distribution};
 
@@ -25,7 +23,7 @@ where
     pub fn wield(&self) {
         loop {
             // Check if we should break out of loop
-            if PROFILER.generator.total_count.load(Ordering::Acquire) == GENERATOR_STOP_COUNT {
+            if PROFILER.generator.total_count() == GENERATOR_STOP_COUNT {
                 return;
             };
 
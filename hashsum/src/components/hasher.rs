@@ -1,4 +1,4 @@
-use std::{iter::Sum, sync::atomic::Ordering};
+use std::iter::Sum;
 
 use bytemuck::{bytes_of, Pod};
 use crossbeam_channel::{bounded, Receiver, Sender, TryRecvError, TrySendError};
@@ -38,7 +38,7 @@ impl<T: Send + Sum + Pod> Hasher<T> {
     pub fn wield(&mut self) {
         loop {
             // Check if we should break out of loop
-            if PROFILER.summer.total_count.load(Ordering::Acquire) == GENERATOR_STOP_COUNT {
+            if PROFILER.summer.total_count() == GENERATOR_STOP_COUNT {
                 return;
             }
 
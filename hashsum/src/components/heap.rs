@@ -1,5 +1,3 @@
-use std::sync::atomic::Ordering;
-
 use crate::{components::hasher::Sha256Hash, GENERATOR_STOP_COUNT, PROFILER};
 
 use crossbeam_channel::Receiver;
@@ -19,7 +17,7 @@ impl Heap {
     pub fn wield(&mut self) {
         loop {
             // Check if we should break out of loop
-            if PROFILER.summer.total_count.load(Ordering::Acquire) == GENERATOR_STOP_COUNT {
+            if PROFILER.summer.total_count() == GENERATOR_STOP_COUNT {
                 return;
             }
 
@@ -35,7 +33,11 @@ impl Heap {
 
                 if self.heap.len() == 1_500_000 {
                     self.heap.append(&mut self.heap.clone());
-                    PROFILER.heap.warn("expensive operation detected");
+                    intuition::warn!(
+                        PROFILER.heap,
+                        len = self.heap.len(),
+                        "expensive operation detected"
+                    );
                 }
 
                 // // drop hash
@@ -1,4 +1,4 @@
-use std::{iter::Sum, sync::atomic::Ordering};
+use std::iter::Sum;
 
 use crossbeam_channel::{bounded, Receiver, Sender, TrySendError};
 
@@ -23,20 +23,22 @@ impl<T: Send + Sum, const S: usize> Summer<T, S> {
     pub fn wield(&self) {
         loop {
             // Check if we should break out of loop
-            if PROFILER.summer.total_count.load(Ordering::Acquire) == GENERATOR_STOP_COUNT {
+            if PROFILER.summer.total_count() == GENERATOR_STOP_COUNT {
                 return;
             }
 
             // Otherwise, perform one iteration
-            PROFILER.summer.iteration(|| {
+            PROFILER.summer.iteration_laps(|laps| {
                 let sample = self
                     .generator_to_summer_rx
                     .recv()
                     .expect("tx never dropped");
+                laps.lap("recv");
                 let mut sample_sum: Option<T> = Some(
                     // Sum sample
                     sample.into_iter().sum(),
                 );
+                laps.lap("sum");
 
                 // // Send sum to hasher
                 // self.summer_to_hasher_tx
@@ -63,6 +65,7 @@ impl<T: Send + Sum, const S: usize> Summer<T, S> {
                         }
                     }
                 }
+                laps.lap("send");
             });
         }
     }
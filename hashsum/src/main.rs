@@ -1,5 +1,5 @@
 use crate::components::{generator::Generator, hasher::Hasher, heap::Heap, summer::Summer};
-use intuition::{construct_profiler, dash::Dash};
+use intuition::{construct_profiler, dash::Dash, logs::RateLimit};
 
 pub mod components;
 
@@ -17,6 +17,12 @@ fn main() {
     let (mut hasher, rx) = Hasher::new(rx);
     let mut heap = Heap::new(rx);
 
+    // The generator warns in a spin loop whenever the summer falls behind
+    PROFILER.generator.set_log_rate_limit(Some(RateLimit {
+        per_second: 1_000,
+        burst: 100,
+    }));
+
     // Initialize dashboard
     let mut dash = Dash::from_profiler(&PROFILER);
 
@@ -0,0 +1,50 @@
+//! Overhead of recording an iteration, single-threaded. Run with
+//! `cargo bench -p intuition`.
+
+use std::hint::black_box;
+
+use criterion::{criterion_group, criterion_main, Criterion};
+use intuition::{
+    clock::{Clock, MockClock, StdClock},
+    timer::Timer,
+};
+
+fn iteration(c: &mut Criterion) {
+    // The floor of every timed iteration
+    let clock = StdClock::default();
+    c.bench_function("two clock reads", |b| {
+        b.iter(|| black_box(clock.now()) + black_box(clock.now()))
+    });
+
+    // Recording alone, without reading a real clock
+    let timer: Timer<1000, 100, MockClock> = Timer::default();
+    c.bench_function("iteration (mock clock)", |b| {
+        b.iter(|| timer.iteration(|| black_box(1)))
+    });
+
+    let timer: Timer<1000, 100> = Timer::default();
+    c.bench_function("iteration", |b| b.iter(|| timer.iteration(|| black_box(1))));
+
+    let timer: Timer<1000, 100> = Timer::default();
+    c.bench_function("guard", |b| b.iter(|| drop(timer.start())));
+
+    let timer: Timer<1000, 100> = Timer::default();
+    c.bench_function("nested iteration", |b| {
+        b.iter(|| timer.iteration(|| timer.iteration(|| black_box(1))))
+    });
+
+    let timer: Timer<1000, 100> = Timer::default();
+    timer.enable_sharding(4);
+    c.bench_function("sharded iteration", |b| {
+        b.iter(|| timer.iteration(|| black_box(1)))
+    });
+
+    let timer: Timer<1000, 100> = Timer::default();
+    timer.set_enabled(false);
+    c.bench_function("disabled scope", |b| {
+        b.iter(|| timer.iteration(|| black_box(1)))
+    });
+}
+
+criterion_group!(benches, iteration);
+criterion_main!(benches);
@@ -0,0 +1,71 @@
+//! Calibration of the overhead of timing an iteration.
+//!
+//! Every recorded iteration time includes part of the cost of reading the
+//! clock around it. That bias is measured once per [Clock], the first time it
+//! is needed (see [Clock::overhead]), and can optionally be subtracted from
+//! every recorded time (see [set_subtract_overhead]).
+//!
+//! Only the clock reads are measured, not a whole [Timer::iteration]: the
+//! recorded time only spans from the first read to the second, with nothing
+//! but the iteration itself in between. Recording the time into the window
+//! and histogram happens after the second read, so it makes iterations
+//! slower but never shows up in their times, and subtracting it as well would
+//! over-correct.
+//!
+//! [Timer::iteration]: crate::timer::Timer::iteration
+
+use std::{
+    hint,
+    sync::atomic::{AtomicBool, Ordering},
+    time::Duration,
+};
+
+use crate::clock::Clock;
+
+/// Number of pairs of clock reads timed to calibrate the overhead
+const CALIBRATION_ITERATIONS: usize = 10_000;
+
+/// Whether the overhead is subtracted from recorded times
+static SUBTRACT_OVERHEAD: AtomicBool = AtomicBool::new(false);
+
+/// Subtract the calibrated overhead of each timer's clock (see
+/// [Clock::overhead]) from the time of every synchronous iteration recorded
+/// from now on (futures are not corrected). Off by default.
+///
+/// Times shorter than the overhead are recorded as zero.
+pub fn set_subtract_overhead(subtract: bool) {
+    SUBTRACT_OVERHEAD.store(subtract, Ordering::Relaxed);
+}
+
+/// Whether the calibrated overhead is subtracted; see [set_subtract_overhead]
+pub fn subtracts_overhead() -> bool {
+    SUBTRACT_OVERHEAD.load(Ordering::Relaxed)
+}
+
+/// Correct a time (in nanoseconds) recorded with `clock` for its overhead, if
+/// enabled
+#[cfg(not(feature = "disabled"))]
+#[inline]
+pub(crate) fn correct(time: u64, clock: &impl Clock) -> u64 {
+    if subtracts_overhead() {
+        time.saturating_sub(clock.overhead().as_nanos().try_into().unwrap_or(u64::MAX))
+    } else {
+        time
+    }
+}
+
+/// Median time between two back-to-back reads of `clock`, which is the time
+/// [crate::timer::Timer::iteration] records for an empty iteration (see the
+/// [module docs](self)).
+pub fn measure_clock_reads(clock: &impl Clock) -> Duration {
+    let mut times: Vec<u64> = (0..CALIBRATION_ITERATIONS)
+        .map(|_| {
+            let start = clock.now();
+            hint::black_box(());
+            clock.now().saturating_sub(start)
+        })
+        .collect();
+    // The median, since preemptions only ever make times longer
+    let middle = times.len() / 2;
+    Duration::from_nanos(*times.select_nth_unstable(middle).1)
+}
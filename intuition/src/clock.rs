@@ -0,0 +1,183 @@
+//! Clock sources a [crate::timer::Timer] can read the time from.
+
+use std::{
+    sync::{
+        atomic::{AtomicU64, Ordering},
+        Arc,
+    },
+    time::{Duration, Instant},
+};
+
+use once_cell::sync::Lazy;
+
+use crate::calibration;
+
+/// A monotonic clock with nanosecond resolution.
+pub trait Clock: Send + Sync {
+    /// Nanoseconds since an arbitrary origin, fixed for the lifetime of the
+    /// clock. Never goes backwards.
+    fn now(&self) -> u64;
+
+    /// Time recorded for an empty iteration when timed with this clock, i.e.
+    /// the bias of every time it records (see [crate::calibration]).
+    fn overhead(&self) -> Duration;
+}
+
+/// The monotonic clock of the standard library ([Instant]). The default.
+///
+/// Clones share the origin, so their times are comparable.
+#[derive(Clone)]
+pub struct StdClock {
+    origin: Instant,
+}
+
+impl Clock for StdClock {
+    #[inline]
+    fn now(&self) -> u64 {
+        self.origin
+            .elapsed()
+            .as_nanos()
+            .try_into()
+            .unwrap_or(u64::MAX)
+    }
+
+    fn overhead(&self) -> Duration {
+        static OVERHEAD: Lazy<Duration> =
+            Lazy::new(|| calibration::measure_clock_reads(&StdClock::default()));
+        *OVERHEAD
+    }
+}
+
+impl Default for StdClock {
+    fn default() -> Self {
+        Self {
+            origin: Instant::now(),
+        }
+    }
+}
+
+/// A clock that only moves when told to, for deterministic tests.
+///
+/// ```rust
+/// # // Timers have no clock nor windows with timing compiled out
+/// # #[cfg(not(feature = "disabled"))] {
+/// use intuition::{clock::MockClock, timer::Timer};
+/// use std::time::Duration;
+///
+/// let timer: Timer<2, 10, MockClock> = Timer::default();
+/// for millis in [1, 3] {
+///     timer.iteration(|| timer.clock.advance(Duration::from_millis(millis)));
+/// }
+/// timer.recent_windows.read(|window| assert!(window.count == 0 || window.mean == 2_000_000));
+/// # }
+/// ```
+#[derive(Default)]
+pub struct MockClock {
+    now: AtomicU64,
+}
+
+impl MockClock {
+    /// Move the clock forward by `duration`, stopping at `u64::MAX`
+    pub fn advance(&self, duration: Duration) {
+        let nanos = duration.as_nanos().try_into().unwrap_or(u64::MAX);
+        let _ = self
+            .now
+            .fetch_update(Ordering::Relaxed, Ordering::Relaxed, |now| {
+                Some(now.saturating_add(nanos))
+            });
+    }
+
+    /// Set the time since the clock's origin. Setting it back in time makes
+    /// iterations in progress look like they took no time at all.
+    pub fn set(&self, since_origin: Duration) {
+        self.now.store(
+            since_origin.as_nanos().try_into().unwrap_or(u64::MAX),
+            Ordering::Relaxed,
+        );
+    }
+}
+
+impl Clock for MockClock {
+    fn now(&self) -> u64 {
+        self.now.load(Ordering::Relaxed)
+    }
+
+    /// Reading the clock does not move it, so there is no bias to correct
+    fn overhead(&self) -> Duration {
+        Duration::ZERO
+    }
+}
+
+/// A shared clock, e.g. a [MockClock] driving several timers at once
+impl<C: Clock + ?Sized> Clock for Arc<C> {
+    #[inline]
+    fn now(&self) -> u64 {
+        (**self).now()
+    }
+
+    fn overhead(&self) -> Duration {
+        (**self).overhead()
+    }
+}
+
+/// The raw time stamp counter of x86_64 CPUs (`rdtsc`), which is cheaper to
+/// read than [StdClock]. Requires the `tsc` feature.
+///
+/// Its frequency is calibrated against [StdClock] the first time one is
+/// created. Only use it on CPUs with an invariant TSC (constant rate,
+/// synchronized across cores), which includes most from the last decade.
+#[cfg(all(feature = "tsc", target_arch = "x86_64"))]
+#[derive(Clone)]
+pub struct TscClock {
+    origin: u64,
+}
+
+#[cfg(all(feature = "tsc", target_arch = "x86_64"))]
+impl TscClock {
+    /// Time spent measuring the frequency of the counter
+    const CALIBRATION_TIME: Duration = Duration::from_millis(10);
+
+    /// Nanoseconds per tick, as a 32.32 fixed point number
+    fn nanos_per_tick() -> u64 {
+        static NANOS_PER_TICK: Lazy<u64> = Lazy::new(|| {
+            let (start, start_ticks) = (Instant::now(), rdtsc());
+            while start.elapsed() < TscClock::CALIBRATION_TIME {
+                std::hint::spin_loop();
+            }
+            let (elapsed, ticks) = (start.elapsed(), rdtsc().saturating_sub(start_ticks));
+            ((elapsed.as_nanos() << 32) / ticks.max(1) as u128) as u64
+        });
+        *NANOS_PER_TICK
+    }
+}
+
+#[cfg(all(feature = "tsc", target_arch = "x86_64"))]
+impl Clock for TscClock {
+    #[inline]
+    fn now(&self) -> u64 {
+        let ticks = rdtsc().saturating_sub(self.origin);
+        ((ticks as u128 * Self::nanos_per_tick() as u128) >> 32) as u64
+    }
+
+    fn overhead(&self) -> Duration {
+        static OVERHEAD: Lazy<Duration> =
+            Lazy::new(|| calibration::measure_clock_reads(&TscClock::default()));
+        *OVERHEAD
+    }
+}
+
+#[cfg(all(feature = "tsc", target_arch = "x86_64"))]
+impl Default for TscClock {
+    fn default() -> Self {
+        // Calibrate before anything is timed
+        Self::nanos_per_tick();
+        Self { origin: rdtsc() }
+    }
+}
+
+#[cfg(all(feature = "tsc", target_arch = "x86_64"))]
+#[inline]
+fn rdtsc() -> u64 {
+    // SAFETY: `rdtsc` is available on every x86_64 CPU
+    unsafe { core::arch::x86_64::_rdtsc() }
+}
@@ -14,7 +14,10 @@ use tui::{
     Terminal,
 };
 
-use super::profiler::{LogBuffer, ProfilerExt, StateBuffer};
+use super::{
+    profiler::{LapBuffer, LogBuffer, PartialBuffer, ProfilerExt, StateBuffer},
+    timer::Statistic,
+};
 
 mod ui;
 
@@ -24,6 +27,12 @@ pub struct Dash<P: ProfilerExt + 'static> {
     profiler: &'static P,
     /// A buffer for the state of your scopes and recent average measurements
     state_buffer: StateBuffer,
+    /// A buffer for the recent throughput (iterations per second) of your scopes
+    throughput_buffer: StateBuffer,
+    /// A buffer for whether the recent windows of your scopes were partial
+    partial_buffer: PartialBuffer,
+    /// A buffer for the recent lap times of your scopes
+    lap_buffer: LapBuffer,
     /// A buffer for the logs of your scopes
     log_buffer: LogBuffer,
     /// Tabs (unused presently but will be used soon)
@@ -34,6 +43,19 @@ pub struct Dash<P: ProfilerExt + 'static> {
     should_quit: bool,
     /// Flags whether the dashboard should show logs for each scope
     show_log: bool,
+    /// Flags whether the dashboard should show a throughput chart for each scope
+    show_throughput: bool,
+    /// Flags whether the dashboard should show a lap chart for scopes with laps
+    show_laps: bool,
+    /// Flags whether to close partial windows on every tick
+    flush_on_tick: bool,
+    /// Index of the scope whose minimum log level is changed with +/-
+    selected_scope: usize,
+    /// Which statistic of each window is plotted
+    statistic: Statistic,
+    /// Calibrated timer overhead in nanoseconds, i.e. the noise floor of the
+    /// plotted times
+    overhead: u64,
     // Just so we don't calc + allocate on every iteration
     domain: Vec<f64>,
 }
@@ -51,15 +73,47 @@ impl<P: ProfilerExt + 'static> Dash<P> {
     /// dash.run(std::time::Duration::from_millis(50));
     /// ```
     pub fn from_profiler(p: &'static impl ::core::ops::Deref<Target = P>) -> Dash<P> {
-        let profiler = p.deref();
+        Dash::new(p.deref())
+    }
+
+    /// Construct a dashboard from a static profiler that is not behind a
+    /// [Deref](core::ops::Deref), such as a [crate::dynamic::Profiler].
+    ///
+    /// ```rust, no_run
+    /// use intuition::{dynamic::Profiler, Dash};
+    ///
+    /// static PROFILER: Profiler<10, 10> = Profiler::new("MyProgram");
+    ///
+    /// let mut dash = Dash::new(&PROFILER);
+    /// dash.run(std::time::Duration::from_millis(50));
+    /// ```
+    pub fn new(profiler: &'static P) -> Dash<P> {
         Dash {
             profiler,
             state_buffer: profiler.state_buffer(),
+            throughput_buffer: profiler.state_buffer(),
+            partial_buffer: profiler
+                .state_buffer()
+                .into_iter()
+                .map(|(scope, _)| (scope, Vec::with_capacity(P::NUM_AVERAGES)))
+                .collect(),
+            lap_buffer: profiler
+                .state_buffer()
+                .into_iter()
+                .map(|(scope, _)| (scope, vec![]))
+                .collect(),
             log_buffer: profiler.log_buffer(),
-            tabs: TabsState::new(vec![P::TITLE]),
+            tabs: TabsState::new(vec![profiler.title()]),
             q_counter: 0,
             should_quit: false,
             show_log: true,
+            show_throughput: true,
+            show_laps: true,
+            flush_on_tick: false,
+            selected_scope: 0,
+            statistic: Statistic::default(),
+            // Calibrate at startup, before the dashboard competes for the CPU
+            overhead: profiler.overhead().as_nanos() as u64,
             domain: (0..P::NUM_AVERAGES).map(|i| i as f64).collect(),
         }
     }
@@ -147,6 +201,63 @@ impl<P: ProfilerExt + 'static> Dash<P> {
                 // If hit enough times, quit
                 self.show_log = !self.show_log;
             }
+            // toggle throughput charts
+            't' => {
+                // Reset q counter on non-q key
+                self.q_counter = 0;
+
+                self.show_throughput = !self.show_throughput;
+            }
+            // toggle lap charts
+            'p' => {
+                // Reset q counter on non-q key
+                self.q_counter = 0;
+
+                self.show_laps = !self.show_laps;
+            }
+            // toggle flushing partial windows on every tick
+            'f' => {
+                // Reset q counter on non-q key
+                self.q_counter = 0;
+
+                self.flush_on_tick = !self.flush_on_tick;
+            }
+            // jump to the error rate
+            'e' => {
+                // Reset q counter on non-q key
+                self.q_counter = 0;
+
+                self.statistic = Statistic::ErrorRate;
+                self.profiler
+                    .update_statistic_buffer(self.statistic, &mut self.state_buffer);
+            }
+            // log less of the selected scope
+            '+' => {
+                // Reset q counter on non-q key
+                self.q_counter = 0;
+
+                let level = self.profiler.min_level(self.selected_scope);
+                self.profiler
+                    .set_min_level(self.selected_scope, level.raise());
+            }
+            // log more of the selected scope
+            '-' => {
+                // Reset q counter on non-q key
+                self.q_counter = 0;
+
+                let level = self.profiler.min_level(self.selected_scope);
+                self.profiler
+                    .set_min_level(self.selected_scope, level.lower());
+            }
+            // cycle plotted statistic
+            's' => {
+                // Reset q counter on non-q key
+                self.q_counter = 0;
+
+                self.statistic = self.statistic.next();
+                self.profiler
+                    .update_statistic_buffer(self.statistic, &mut self.state_buffer);
+            }
             _ => {
                 // Reset q counter on any other key
                 self.q_counter = 0;
@@ -157,11 +268,14 @@ impl<P: ProfilerExt + 'static> Dash<P> {
     }
 
     fn on_up(&mut self) {
-        // do nothing for now
+        // Select the previous scope, wrapping around
+        let num_scopes = self.log_buffer.len().max(1);
+        self.selected_scope = (self.selected_scope + num_scopes - 1) % num_scopes;
     }
 
     fn on_down(&mut self) {
-        // do nothing for now
+        // Select the next scope, wrapping around
+        self.selected_scope = (self.selected_scope + 1) % self.log_buffer.len().max(1);
     }
 
     fn on_right(&mut self) {
@@ -173,10 +287,26 @@ impl<P: ProfilerExt + 'static> Dash<P> {
     }
 
     fn on_tick(&mut self) {
+        // Make sure idle time-based scopes still emit windows
+        self.profiler.close_elapsed_windows();
+        if self.flush_on_tick {
+            self.profiler.flush();
+        }
         // Update state buffer
-        self.profiler.update_buffer(&mut self.state_buffer);
+        self.profiler
+            .update_statistic_buffer(self.statistic, &mut self.state_buffer);
+        // Update throughput buffer
+        self.profiler
+            .update_throughput_buffer(&mut self.throughput_buffer);
+        // Update partial buffer
+        self.profiler
+            .update_partial_buffer(&mut self.partial_buffer);
+        // Update lap buffer
+        self.profiler.update_lap_buffer(&mut self.lap_buffer);
         // Update log buffer
         self.profiler.update_logs(&mut self.log_buffer);
+        // Scopes (and with them clocks) may have been added
+        self.overhead = self.profiler.overhead().as_nanos() as u64;
     }
 }
 
@@ -186,7 +316,7 @@ struct TabsState<'a> {
 }
 
 impl<'a> TabsState<'a> {
-    fn new(titles: Vec<&'a str>) -> TabsState {
+    fn new(titles: Vec<&'a str>) -> TabsState<'a> {
         TabsState { titles, index: 0 }
     }
     fn next(&mut self) {
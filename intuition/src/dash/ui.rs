@@ -1,17 +1,39 @@
+use std::time::{SystemTime, UNIX_EPOCH};
+
 use tui::{
     backend::Backend,
     layout::{Constraint, Direction, Layout, Rect},
     style::{Color, Modifier, Style},
     symbols,
     text::{Span, Spans},
-    widgets::{Axis, Block, Borders, Chart, Dataset, List, ListItem, Tabs},
+    widgets::{Axis, Block, Borders, Chart, Dataset, GraphType, List, ListItem, Tabs},
     Frame,
 };
 
-use crate::{profiler::ProfilerExt, timer::LogLevel};
+use crate::{
+    calibration,
+    profiler::ProfilerExt,
+    timer::{Log, LogLevel, Sampling},
+};
 
 use super::Dash;
 
+/// Times within this factor of the timer overhead are marked as being close
+/// to the noise floor
+const NOISE_FLOOR_FACTOR: u64 = 10;
+
+const TRACE_LOG_STYLE: Style = Style {
+    fg: Some(Color::Gray),
+    bg: None,
+    add_modifier: Modifier::empty(),
+    sub_modifier: Modifier::empty(),
+};
+const DEBUG_LOG_STYLE: Style = Style {
+    fg: Some(Color::Green),
+    bg: None,
+    add_modifier: Modifier::empty(),
+    sub_modifier: Modifier::empty(),
+};
 const INFO_LOG_STYLE: Style = Style {
     fg: Some(Color::Blue),
     bg: None,
@@ -31,6 +53,14 @@ const ERROR_LOG_STYLE: Style = Style {
     sub_modifier: Modifier::empty(),
 };
 
+/// Style of the time, thread and location of a log
+const LOG_DETAIL_STYLE: Style = Style {
+    fg: Some(Color::DarkGray),
+    bg: None,
+    add_modifier: Modifier::empty(),
+    sub_modifier: Modifier::empty(),
+};
+
 pub(super) fn draw<B, P>(f: &mut Frame<B>, dash: &mut Dash<P>)
 where
     B: Backend,
@@ -45,8 +75,18 @@ where
         .iter()
         .map(|t| Spans::from(Span::styled(*t, Style::default().fg(Color::Green))))
         .collect();
+    let title = format!(
+        "{} (timer overhead ~{}ns{})",
+        dash.profiler.title(),
+        dash.overhead,
+        if calibration::subtracts_overhead() {
+            ", subtracted"
+        } else {
+            ""
+        }
+    );
     let tabs = Tabs::new(titles)
-        .block(Block::default().borders(Borders::ALL).title(P::TITLE))
+        .block(Block::default().borders(Borders::ALL).title(title))
         .highlight_style(Style::default().fg(Color::Yellow))
         .select(dash.tabs.index);
 
@@ -54,10 +94,9 @@ where
     f.render_widget(tabs, chunks[0]);
 
     // Render particular tab
-    match dash.tabs.index {
-        0 => draw_first_tab(f, dash, chunks[1]),
-        _ => {}
-    };
+    if dash.tabs.index == 0 {
+        draw_first_tab(f, dash, chunks[1])
+    }
 }
 
 fn draw_first_tab<B, P>(f: &mut Frame<B>, dash: &mut Dash<P>, area: Rect)
@@ -86,92 +125,99 @@ where
         .constraints(constraints)
         .direction(Direction::Horizontal)
         .split(area);
+    // Scopes can be added at runtime, so go by what the buffers hold
+    let num_scopes = app.state_buffer.len();
     'plot_scope: {
         // Get the chunks for each scope
-        let percentage_per_scope = 100 / P::SCOPES.len() as u16;
+        let percentage_per_scope = 100 / num_scopes.max(1) as u16;
         let chunks = Layout::default()
-            .constraints(
-                vec![Constraint::Percentage(percentage_per_scope); P::SCOPES.len()].as_ref(),
-            )
+            .constraints(vec![
+                Constraint::Percentage(percentage_per_scope);
+                num_scopes
+            ])
             .split(chunks[0]);
 
         // Colors we cycle through
         const COLORS: [Color; 4] = [Color::Cyan, Color::Red, Color::Yellow, Color::Magenta];
 
-        for chunk in 0..chunks.len() {
+        for (chunk, area) in chunks.iter().enumerate() {
             // Scope for this chunk
             let (scope_name, averages) = &app.state_buffer[chunk];
-            let max_average = *averages.iter().max().unwrap_or(&0) as f64;
-
-            let pairs: Vec<(f64, f64)> = app
-                .domain
-                .clone()
-                .into_iter()
-                .zip(
-                    averages
-                        .into_iter()
-                        .map(|a| *a as f64)
-                        .collect::<Vec<f64>>(),
-                )
-                .filter(|(_x, y)| *y > 0.00)
-                .collect();
+            let mut title = scope_title(scope_name, app.profiler.depth(chunk));
+            let sampling = app.profiler.sampling(chunk);
+            if sampling != Sampling::All {
+                title = format!("{title} (sampled {sampling})");
+            }
+            let color = COLORS[chunk % COLORS.len()];
+            let partial = app
+                .partial_buffer
+                .get(chunk)
+                .map_or(&[][..], |(_, partial)| partial);
+            let throughputs = app
+                .throughput_buffer
+                .get(chunk)
+                .map_or(&[][..], |(_, throughputs)| throughputs);
 
-            let dataset: Dataset = Dataset::default()
-                .name(*scope_name)
-                .marker(symbols::Marker::Braille)
-                .style(Style::default().fg(COLORS[chunk % COLORS.len()]))
-                .data(&pairs);
-
-            let x_labels = if P::NUM_AVERAGES > 50 {
-                vec![
-                    Span::styled(
-                        format!("{}", P::NUM_AVERAGES as f64),
-                        Style::default().add_modifier(Modifier::BOLD),
-                    ),
-                    Span::raw(format!("{}", P::NUM_AVERAGES as f64 / 2.0)),
-                    Span::styled("0", Style::default().add_modifier(Modifier::BOLD)),
-                ]
+            // If the scope has laps, show them below its other charts
+            let laps = app.lap_buffer.get(chunk).map_or(&[][..], |(_, laps)| laps);
+            let area = if app.show_laps && !laps.is_empty() {
+                let areas = Layout::default()
+                    .constraints([Constraint::Percentage(50), Constraint::Percentage(50)])
+                    .direction(Direction::Vertical)
+                    .split(*area);
+                draw_lap_chart::<B, P>(f, areas[1], &title, &app.domain, laps);
+                areas[0]
             } else {
-                vec![
-                    Span::styled(
-                        format!("{}", P::NUM_AVERAGES as f64),
-                        Style::default().add_modifier(Modifier::BOLD),
-                    ),
-                    Span::styled("0", Style::default().add_modifier(Modifier::BOLD)),
-                ]
+                *area
             };
 
-            let chart = Chart::new(vec![dataset])
-                .block(
-                    Block::default()
-                        .title(Span::styled(
-                            format!("{scope_name}"),
-                            Style::default()
-                                .fg(Color::Cyan)
-                                .add_modifier(Modifier::BOLD),
-                        ))
-                        .borders(Borders::ALL),
-                )
-                .x_axis(
-                    Axis::default()
-                        .title("History of Averages")
-                        .style(Style::default().fg(Color::Gray))
-                        .bounds([0.0, P::NUM_AVERAGES as f64])
-                        .labels(x_labels),
-                )
-                .y_axis(
-                    Axis::default()
-                        .title("Average")
-                        .style(Style::default().fg(Color::Gray))
-                        .bounds([0.0, max_average * 1.5])
-                        .labels(vec![
-                            Span::raw("0"),
-                            Span::raw(format!("{}", max_average * 0.5)),
-                            Span::raw(format!("{}", max_average)),
-                            Span::raw(format!("{}", max_average * 1.5)),
-                        ]),
+            // If show throughput, split the scope's area horizontally
+            let constraints = if app.show_throughput {
+                vec![Constraint::Percentage(50), Constraint::Percentage(50)]
+            } else {
+                vec![Constraint::Percentage(100)]
+            };
+            let areas = Layout::default()
+                .constraints(constraints)
+                .direction(Direction::Horizontal)
+                .split(area);
+
+            draw_chart::<B, P>(
+                f,
+                areas[0],
+                ChartSpec {
+                    title: &title,
+                    y_title: app.statistic.to_string(),
+                    // Zeros are meaningful for counts and rates, e.g. a flat
+                    // error rate of 0
+                    keep_zeros: !app.statistic.is_time(),
+                    throughputs,
+                    noise_floor: app.statistic.is_time().then_some(app.overhead),
+                    partial,
+                    color,
+                },
+                &app.domain,
+                averages,
+            );
+
+            if app.show_throughput {
+                draw_chart::<B, P>(
+                    f,
+                    areas[1],
+                    ChartSpec {
+                        title: &title,
+                        y_title: "Iterations/s".to_string(),
+                        // A starved scope should show up as zero throughput
+                        keep_zeros: true,
+                        throughputs,
+                        noise_floor: None,
+                        partial,
+                        color,
+                    },
+                    &app.domain,
+                    throughputs,
                 );
-            f.render_widget(chart, chunks[chunk]);
+            }
         }
     }
 
@@ -179,35 +225,360 @@ where
     if app.show_log {
         // // Draw logs
         // Get the chunks for each scope
-        let percentage_per_scope = 100 / P::SCOPES.len() as u16;
+        let percentage_per_scope = 100 / num_scopes.max(1) as u16;
         let chunks = Layout::default()
-            .constraints(
-                vec![Constraint::Percentage(percentage_per_scope); P::SCOPES.len()].as_ref(),
-            )
+            .constraints(vec![
+                Constraint::Percentage(percentage_per_scope);
+                num_scopes
+            ])
             .split(chunks[1]);
 
-        for chunk in 0..chunks.len() {
-            let (scope_name, ref scope_logs) = &app.log_buffer[chunk];
+        for (chunk, area) in chunks.iter().enumerate() {
+            let Some((scope_name, ref scope_logs)) = app.log_buffer.get(chunk) else {
+                continue;
+            };
+            let mut title = format!(
+                "{} (≥ {}",
+                scope_title(scope_name, app.profiler.depth(chunk)),
+                app.profiler.min_level(chunk)
+            );
+            if scope_logs.cursor.dropped > 0 {
+                title += &format!(", {} dropped", scope_logs.cursor.dropped);
+            }
+            if scope_logs.rate_limited > 0 {
+                title += &format!(", {} rate limited", scope_logs.rate_limited);
+            }
+            title += ")";
+            // The selected scope's level is changed with +/-
+            let border_style = if chunk == app.selected_scope {
+                Style::default().fg(Color::Yellow)
+            } else {
+                Style::default()
+            };
 
-            let logs: Vec<ListItem> = scope_logs[scope_logs.len().saturating_sub(100)..]
+            let logs: Vec<ListItem> = scope_logs
+                .logs
                 .iter()
                 .map(|log| {
                     let s = match log.level {
                         LogLevel::Error => ERROR_LOG_STYLE,
                         LogLevel::Warn => WARN_LOG_STYLE,
                         LogLevel::Info => INFO_LOG_STYLE,
+                        LogLevel::Debug => DEBUG_LOG_STYLE,
+                        LogLevel::Trace => TRACE_LOG_STYLE,
                     };
-                    let content = vec![Spans::from(vec![
-                        Span::styled(format!("{:<9}", log.level), s),
+                    let mut spans = vec![
+                        Span::styled(
+                            format!(
+                                "{} +{:.3}s ",
+                                wall_clock(log.time),
+                                log.monotonic as f64 / 1e9
+                            ),
+                            LOG_DETAIL_STYLE,
+                        ),
+                        Span::styled(format!("{:<6}", log.level), s),
+                        Span::styled(format!("{} ", thread_label(log)), LOG_DETAIL_STYLE),
                         Span::raw(log.log.clone()),
-                    ])];
-                    ListItem::new(content)
+                    ];
+                    for (key, value) in &log.fields {
+                        spans.push(Span::styled(format!(" {key}="), LOG_DETAIL_STYLE));
+                        spans.push(Span::raw(value.clone()));
+                    }
+                    // Repeats of the same log are shown once
+                    if log.count > 1 {
+                        spans.push(Span::styled(format!(" (x{})", log.count), s));
+                        spans.push(Span::styled(
+                            format!(" until {}", wall_clock(log.last_time)),
+                            LOG_DETAIL_STYLE,
+                        ));
+                    }
+                    spans.push(Span::styled(
+                        format!(" {}:{}", log.file, log.line),
+                        LOG_DETAIL_STYLE,
+                    ));
+                    ListItem::new(Spans::from(spans))
                 })
                 .collect();
-            let logs =
-                List::new(logs).block(Block::default().borders(Borders::ALL).title(*scope_name));
+            let logs = List::new(logs).block(
+                Block::default()
+                    .borders(Borders::ALL)
+                    .border_style(border_style)
+                    .title(title),
+            );
             // f.render_stateful_widget(logs, chunks[1], &mut app.logs.state);
-            f.render_widget(logs, chunks[chunk]);
+            f.render_widget(logs, *area);
         }
     }
 }
+
+/// Time of day of a wall-clock time as `HH:MM:SS.mmm`, in UTC
+fn wall_clock(time: SystemTime) -> String {
+    let since_epoch = time.duration_since(UNIX_EPOCH).unwrap_or_default();
+    let seconds = since_epoch.as_secs() % (24 * 60 * 60);
+    format!(
+        "{:02}:{:02}:{:02}.{:03}",
+        seconds / 3600,
+        seconds / 60 % 60,
+        seconds % 60,
+        since_epoch.subsec_millis()
+    )
+}
+
+/// The name of the thread a log came from, or its id if it has none
+fn thread_label(log: &Log) -> String {
+    match &log.thread_name {
+        Some(name) => name.to_string(),
+        None => format!("{:?}", log.thread_id),
+    }
+}
+
+/// Title of a scope, indented according to its depth in the scope tree
+fn scope_title(name: &str, depth: usize) -> String {
+    if depth == 0 {
+        name.to_string()
+    } else {
+        format!("{}└ {name}", "  ".repeat(depth - 1))
+    }
+}
+
+/// How a single chart of per-window values should be drawn
+struct ChartSpec<'a> {
+    title: &'a str,
+    y_title: String,
+    /// Whether zero values are plotted (except the ones before the history
+    /// filled up) or treated as missing
+    keep_zeros: bool,
+    /// Throughput of each window, which tells the windows before the history
+    /// filled up (which had no iterations) from ones with a value of zero
+    throughputs: &'a [u64],
+    /// Timer overhead, if the values are times. Drawn as a line, and the
+    /// chart is marked if the latest value is close to it.
+    noise_floor: Option<u64>,
+    /// Whether each value comes from a partial window; those are drawn with
+    /// a different marker
+    partial: &'a [bool],
+    color: Color,
+}
+
+fn draw_chart<B, P>(f: &mut Frame<B>, area: Rect, spec: ChartSpec, domain: &[f64], values: &[u64])
+where
+    B: Backend,
+    P: ProfilerExt,
+{
+    let max_value = values
+        .iter()
+        .copied()
+        .chain(spec.noise_floor)
+        .max()
+        .unwrap_or(0) as f64;
+
+    let points = domain
+        .iter()
+        .copied()
+        .zip(values.iter().map(|v| *v as f64))
+        .enumerate()
+        .map(|(i, (x, y))| (i, x, y));
+    let points: Vec<(usize, f64, f64)> = if spec.keep_zeros {
+        let happened = |i: usize| spec.throughputs.get(i).is_some_and(|t| *t > 0);
+        points
+            .skip_while(|(i, _x, y)| *y == 0.00 && !happened(*i))
+            .collect()
+    } else {
+        points.filter(|(_i, _x, y)| *y > 0.00).collect()
+    };
+    let points: Vec<(f64, f64, bool)> = points
+        .into_iter()
+        .map(|(i, x, y)| (x, y, spec.partial.get(i).copied().unwrap_or(false)))
+        .collect();
+    // Partial windows are drawn separately
+    let pairs_where = |partial: bool| -> Vec<(f64, f64)> {
+        points
+            .iter()
+            .filter(|(_x, _y, p)| *p == partial)
+            .map(|(x, y, _)| (*x, *y))
+            .collect()
+    };
+    let (pairs, partial_pairs) = (pairs_where(false), pairs_where(true));
+
+    let dataset: Dataset = Dataset::default()
+        .name(spec.title)
+        .marker(symbols::Marker::Braille)
+        .style(Style::default().fg(spec.color))
+        .data(&pairs);
+    let mut datasets = vec![dataset];
+    if !partial_pairs.is_empty() {
+        datasets.push(
+            Dataset::default()
+                .name("partial windows")
+                .marker(symbols::Marker::Dot)
+                .style(Style::default().fg(spec.color).add_modifier(Modifier::DIM))
+                .data(&partial_pairs),
+        );
+    }
+
+    let mut title = Span::styled(
+        spec.title,
+        Style::default()
+            .fg(Color::Cyan)
+            .add_modifier(Modifier::BOLD),
+    );
+    let floor_pairs: Vec<(f64, f64)>;
+    if let Some(floor) = spec.noise_floor {
+        floor_pairs = vec![(0.0, floor as f64), (P::NUM_AVERAGES as f64, floor as f64)];
+        datasets.push(
+            Dataset::default()
+                .name("timer overhead")
+                .marker(symbols::Marker::Braille)
+                .graph_type(GraphType::Line)
+                .style(Style::default().fg(Color::DarkGray))
+                .data(&floor_pairs),
+        );
+
+        let latest = points.last().map(|(_x, y, _)| *y as u64);
+        if latest.is_some_and(|latest| latest < floor.saturating_mul(NOISE_FLOOR_FACTOR)) {
+            title = Span::styled(
+                format!("{} (near noise floor)", spec.title),
+                Style::default()
+                    .fg(Color::Yellow)
+                    .add_modifier(Modifier::BOLD),
+            );
+        }
+    }
+
+    let chart = Chart::new(datasets)
+        .block(Block::default().title(title).borders(Borders::ALL))
+        .x_axis(
+            Axis::default()
+                .title("History of Averages")
+                .style(Style::default().fg(Color::Gray))
+                .bounds([0.0, P::NUM_AVERAGES as f64])
+                .labels(x_labels::<P>()),
+        )
+        .y_axis(
+            Axis::default()
+                .title(spec.y_title)
+                .style(Style::default().fg(Color::Gray))
+                .bounds([0.0, max_value * 1.5])
+                .labels(vec![
+                    Span::raw("0"),
+                    Span::raw(format!("{}", max_value * 0.5)),
+                    Span::raw(format!("{}", max_value)),
+                    Span::raw(format!("{}", max_value * 1.5)),
+                ]),
+        );
+    f.render_widget(chart, area);
+}
+
+/// Draw the average time of each lap of a scope in recent windows, stacked on
+/// top of each other in the order the laps were first seen
+fn draw_lap_chart<B, P>(
+    f: &mut Frame<B>,
+    area: Rect,
+    title: &str,
+    domain: &[f64],
+    laps: &[(&str, Vec<u64>)],
+) where
+    B: Backend,
+    P: ProfilerExt,
+{
+    // Colors we cycle through, one per lap
+    const COLORS: [Color; 6] = [
+        Color::Green,
+        Color::Blue,
+        Color::Magenta,
+        Color::Yellow,
+        Color::Cyan,
+        Color::Red,
+    ];
+
+    // Only plot windows in which any lap was timed
+    let mut stacked = vec![0u64; domain.len()];
+    for (_, values) in laps {
+        for (total, value) in stacked.iter_mut().zip(values) {
+            *total = total.saturating_add(*value);
+        }
+    }
+    let timed: Vec<bool> = stacked.iter().map(|total| *total > 0).collect();
+    let max_value = stacked.iter().copied().max().unwrap_or(0) as f64;
+
+    // Each lap is drawn at the sum of its time and that of the laps before it
+    stacked.fill(0);
+    let series: Vec<(&str, Vec<(f64, f64)>)> = laps
+        .iter()
+        .map(|(name, values)| {
+            for (total, value) in stacked.iter_mut().zip(values) {
+                *total = total.saturating_add(*value);
+            }
+            let points = domain
+                .iter()
+                .zip(&stacked)
+                .zip(&timed)
+                .filter(|(_, timed)| **timed)
+                .map(|((x, y), _)| (*x, *y as f64))
+                .collect();
+            (*name, points)
+        })
+        .collect();
+    let datasets = series
+        .iter()
+        .enumerate()
+        .map(|(index, (name, points))| {
+            Dataset::default()
+                .name(*name)
+                .marker(symbols::Marker::Braille)
+                .style(Style::default().fg(COLORS[index % COLORS.len()]))
+                .data(points)
+        })
+        .collect();
+
+    let title = Span::styled(
+        format!("{title} laps"),
+        Style::default()
+            .fg(Color::Cyan)
+            .add_modifier(Modifier::BOLD),
+    );
+    let chart = Chart::new(datasets)
+        .block(Block::default().title(title).borders(Borders::ALL))
+        .x_axis(
+            Axis::default()
+                .title("History of Averages")
+                .style(Style::default().fg(Color::Gray))
+                .bounds([0.0, P::NUM_AVERAGES as f64])
+                .labels(x_labels::<P>()),
+        )
+        .y_axis(
+            Axis::default()
+                .title("Stacked lap averages")
+                .style(Style::default().fg(Color::Gray))
+                .bounds([0.0, max_value * 1.5])
+                .labels(vec![
+                    Span::raw("0"),
+                    Span::raw(format!("{}", max_value * 0.5)),
+                    Span::raw(format!("{}", max_value)),
+                    Span::raw(format!("{}", max_value * 1.5)),
+                ]),
+        );
+    f.render_widget(chart, area);
+}
+
+/// Labels of the x axis, which spans the history of windows
+fn x_labels<P: ProfilerExt>() -> Vec<Span<'static>> {
+    if P::NUM_AVERAGES > 50 {
+        vec![
+            Span::styled(
+                format!("{}", P::NUM_AVERAGES as f64),
+                Style::default().add_modifier(Modifier::BOLD),
+            ),
+            Span::raw(format!("{}", P::NUM_AVERAGES as f64 / 2.0)),
+            Span::styled("0", Style::default().add_modifier(Modifier::BOLD)),
+        ]
+    } else {
+        vec![
+            Span::styled(
+                format!("{}", P::NUM_AVERAGES as f64),
+                Style::default().add_modifier(Modifier::BOLD),
+            ),
+            Span::styled("0", Style::default().add_modifier(Modifier::BOLD)),
+        ]
+    }
+}
@@ -0,0 +1,294 @@
+use std::{
+    borrow::Cow,
+    ops::Deref,
+    sync::{Arc, OnceLock, RwLock},
+    time::Duration,
+};
+
+use crate::{
+    clock::{Clock, StdClock},
+    profiler::{LapBuffer, LogBuffer, PartialBuffer, ProfilerExt, ScopeExt, StateBuffer},
+    timer::{LogLevel, Sampling, Statistic, Timer},
+};
+
+/// A profiler whose scopes are registered by name at runtime, e.g. one per
+/// worker, shard or plugin.
+///
+/// ```rust, no_run
+/// use intuition::{dynamic::Profiler, Dash};
+///
+/// static PROFILER: Profiler<10, 10> = Profiler::new("Workers");
+///
+/// for worker in 0..4 {
+///     let scope = PROFILER.scope(format!("worker-{worker}"));
+///     std::thread::spawn(move || loop {
+///         scope.iteration(|| std::thread::sleep(std::time::Duration::from_millis(10)));
+///     });
+/// }
+///
+/// Dash::new(&PROFILER).run(std::time::Duration::from_millis(50)).unwrap();
+/// ```
+pub struct Profiler<const W: usize, const A: usize, C = StdClock> {
+    title: &'static str,
+    /// Clock cloned into every scope, made with `Default` on first use unless
+    /// given to [Profiler::with_clock]
+    clock: OnceLock<C>,
+    /// Registered scopes, in order of registration
+    scopes: RwLock<Vec<(&'static str, Scope<W, A, C>)>>,
+}
+
+/// A cheap, cloneable handle to a scope of a dynamic [Profiler]. Derefs to
+/// the scope's [Timer].
+pub struct Scope<const W: usize, const A: usize, C = StdClock>(Arc<Timer<W, A, C>>);
+
+impl<const W: usize, const A: usize, C> Profiler<W, A, C> {
+    pub const fn new(title: &'static str) -> Self {
+        Self {
+            title,
+            clock: OnceLock::new(),
+            scopes: RwLock::new(Vec::new()),
+        }
+    }
+
+    /// A profiler whose scopes all read the time from `clock` (or a clone of
+    /// it, e.g. an `Arc<MockClock>`)
+    pub fn with_clock(title: &'static str, clock: C) -> Self {
+        Self {
+            title,
+            clock: OnceLock::from(clock),
+            scopes: RwLock::new(Vec::new()),
+        }
+    }
+}
+
+impl<const W: usize, const A: usize, C: Clock + Default + Clone> Profiler<W, A, C> {
+    /// Get the scope called `name`, registering it if it does not exist yet.
+    ///
+    /// Scopes are never unregistered, and their names are handed out as
+    /// `&'static str` to the [crate::Dash]. An owned name (e.g. built with
+    /// `format!`) is leaked for that when its scope is registered, but not
+    /// when an existing scope is looked up.
+    pub fn scope(&self, name: impl Into<Cow<'static, str>>) -> Scope<W, A, C> {
+        let name = name.into();
+        if let Some(scope) = self.get(&name) {
+            return scope;
+        }
+
+        let mut scopes = self.scopes.write().unwrap();
+        // Someone may have registered it while we waited for the lock
+        if let Some((_, scope)) = scopes.iter().find(|(n, _)| *n == name) {
+            return scope.clone();
+        }
+        let name = match name {
+            Cow::Borrowed(name) => name,
+            Cow::Owned(name) => Box::leak(name.into_boxed_str()),
+        };
+        let clock = self.clock.get_or_init(C::default).clone();
+        let scope = Scope(Arc::new(Timer::with_clock(clock)));
+        scopes.push((name, scope.clone()));
+        scope
+    }
+
+    /// Get the scope called `name`, if it has been registered
+    pub fn get(&self, name: &str) -> Option<Scope<W, A, C>> {
+        self.scopes
+            .read()
+            .unwrap()
+            .iter()
+            .find(|(n, _)| *n == name)
+            .map(|(_, scope)| scope.clone())
+    }
+
+    /// Number of registered scopes
+    pub fn len(&self) -> usize {
+        self.scopes.read().unwrap().len()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.len() == 0
+    }
+
+    /// Make sure `buffer` has an entry for every registered scope, then call
+    /// `f` with each scope and its entry.
+    fn update<T: Default>(
+        &self,
+        buffer: &mut Vec<(&'static str, T)>,
+        mut f: impl FnMut(&Timer<W, A, C>, &mut T),
+    ) {
+        let scopes = self.scopes.read().unwrap();
+        // Scopes are only ever appended, so new ones go at the end
+        for (name, _) in &scopes[buffer.len().min(scopes.len())..] {
+            buffer.push((name, T::default()));
+        }
+        for ((name, scope), (scope_name, values)) in scopes.iter().zip(buffer.iter_mut()) {
+            // Ensure we are updating proper scope
+            assert_eq!(name, scope_name);
+            f(scope, values);
+        }
+    }
+}
+
+impl<const W: usize, const A: usize, C> Clone for Scope<W, A, C> {
+    fn clone(&self) -> Self {
+        Self(Arc::clone(&self.0))
+    }
+}
+
+impl<const W: usize, const A: usize, C> Deref for Scope<W, A, C> {
+    type Target = Timer<W, A, C>;
+    fn deref(&self) -> &Self::Target {
+        &self.0
+    }
+}
+
+impl<const W: usize, const A: usize, C: Clock + Default + Clone> ProfilerExt for Profiler<W, A, C> {
+    // Scopes are only known at runtime
+    const SCOPES: &'static [&'static str] = &[];
+    const DEPTHS: &'static [usize] = &[];
+    const TITLE: &'static str = "";
+    const WINDOW_SIZE: usize = W;
+    const NUM_AVERAGES: usize = A;
+
+    fn title(&self) -> &'static str {
+        self.title
+    }
+
+    fn depth(&self, _index: usize) -> usize {
+        0
+    }
+
+    fn update_logs(&self, buffer: &mut LogBuffer) {
+        self.update(buffer, |scope, logs| scope.update_logs(logs));
+    }
+
+    fn update_buffer(&self, buffer: &mut StateBuffer) {
+        self.update_statistic_buffer(Statistic::Mean, buffer);
+    }
+
+    fn update_statistic_buffer(&self, statistic: Statistic, buffer: &mut StateBuffer) {
+        self.update(buffer, |scope, values| {
+            values.clear();
+            scope.read_windows(&mut |window| values.push(statistic.of(&window)));
+        });
+    }
+
+    fn update_throughput_buffer(&self, buffer: &mut StateBuffer) {
+        self.update(buffer, |scope, values| {
+            values.clear();
+            scope.read_windows(&mut |window| values.push(window.throughput()));
+        });
+    }
+
+    fn update_partial_buffer(&self, buffer: &mut PartialBuffer) {
+        self.update(buffer, |scope, partial| {
+            partial.clear();
+            scope.read_windows(&mut |window| partial.push(window.partial));
+        });
+    }
+
+    fn update_lap_buffer(&self, buffer: &mut LapBuffer) {
+        self.update(buffer, |scope, laps| scope.update_lap_buffer(laps));
+    }
+
+    fn sampling(&self, index: usize) -> Sampling {
+        self.scopes
+            .read()
+            .unwrap()
+            .get(index)
+            .map(|(_, scope)| scope.sampling())
+            .unwrap_or_default()
+    }
+
+    fn min_level(&self, index: usize) -> LogLevel {
+        self.scopes
+            .read()
+            .unwrap()
+            .get(index)
+            .map(|(_, scope)| scope.min_level())
+            .unwrap_or_default()
+    }
+
+    fn set_min_level(&self, index: usize, level: LogLevel) {
+        if let Some((_, scope)) = self.scopes.read().unwrap().get(index) {
+            scope.set_min_level(level);
+        }
+    }
+
+    fn log_enabled(&self, index: usize, level: LogLevel) -> bool {
+        self.scopes
+            .read()
+            .unwrap()
+            .get(index)
+            .is_some_and(|(_, scope)| scope.log_enabled(level))
+    }
+
+    fn log_fields_at(
+        &self,
+        index: usize,
+        level: LogLevel,
+        log: Cow<'static, str>,
+        fields: Vec<(&'static str, String)>,
+        file: &'static str,
+        line: u32,
+    ) {
+        if let Some((_, scope)) = self.scopes.read().unwrap().get(index) {
+            scope.log_fields_at(level, log, fields, file, line);
+        }
+    }
+
+    fn now(&self, index: usize) -> Option<u64> {
+        self.scopes
+            .read()
+            .unwrap()
+            .get(index)
+            .map(|(_, scope)| scope.now())
+    }
+
+    fn record_iteration(&self, index: usize, start: u64, end: u64, busy: u64) {
+        if let Some((_, scope)) = self.scopes.read().unwrap().get(index) {
+            scope.record_iteration(start, end, busy);
+        }
+    }
+
+    fn scope_index(&self, name: &str) -> Option<usize> {
+        self.scopes
+            .read()
+            .unwrap()
+            .iter()
+            .position(|(scope, _)| *scope == name)
+    }
+
+    fn overhead(&self) -> Duration {
+        self.scopes
+            .read()
+            .unwrap()
+            .iter()
+            .map(|(_, scope)| scope.overhead())
+            .max()
+            .unwrap_or_default()
+    }
+
+    fn close_elapsed_windows(&self) {
+        for (_, scope) in self.scopes.read().unwrap().iter() {
+            scope.close_elapsed_windows();
+        }
+    }
+
+    fn flush(&self) {
+        for (_, scope) in self.scopes.read().unwrap().iter() {
+            scope.flush();
+        }
+    }
+
+    fn state_buffer(&self) -> StateBuffer {
+        let mut buffer = vec![];
+        self.update(&mut buffer, |_, _| {});
+        buffer
+    }
+
+    fn log_buffer(&self) -> LogBuffer {
+        let mut buffer = vec![];
+        self.update(&mut buffer, |_, _| {});
+        buffer
+    }
+}
@@ -0,0 +1,76 @@
+use std::time::Duration;
+
+use crate::{
+    clock::Clock,
+    nesting::{self, Frame},
+    timer::Timer,
+};
+
+/// Times a single iteration of a [Timer] from its creation (via [Timer::start])
+/// until it is dropped.
+///
+/// A guard ends the iteration on the thread that started it, so it is not
+/// `Send`: it cannot be held across an `.await` in a future that has to be.
+/// Use [Timer::instrument] to time futures instead.
+#[must_use = "the iteration ends as soon as the guard is dropped"]
+pub struct IterationGuard<'t, const W: usize, const A: usize, C: Clock> {
+    timer: &'t Timer<W, A, C>,
+    /// The iteration, or `None` if it is not timed or has already ended
+    running: Option<Running>,
+}
+
+struct Running {
+    /// When the iteration started, according to the timer's clock
+    start: u64,
+    /// Number of iterations this one stands for; see [Timer::set_sampling]
+    iterations: usize,
+    frame: Frame,
+}
+
+impl<'t, const W: usize, const A: usize, C: Clock> IterationGuard<'t, W, A, C> {
+    pub(crate) fn new(timer: &'t Timer<W, A, C>) -> Self {
+        let running = timer.sample().map(|iterations| {
+            let frame = nesting::enter();
+            Running {
+                start: timer.clock.now(),
+                iterations,
+                frame,
+            }
+        });
+        Self { timer, running }
+    }
+
+    /// End the iteration now, returning how long it took (zero if it was not
+    /// timed).
+    pub fn finish(mut self) -> Duration {
+        let Some(running) = self.running.take() else {
+            return Duration::ZERO;
+        };
+        let end = self.timer.clock.now();
+        let start = running.start;
+        self.timer
+            .record(running.frame, start, end, running.iterations, false);
+        Duration::from_nanos(end.saturating_sub(start))
+    }
+
+    /// Discard the iteration; nothing is recorded. Any time spent in it is
+    /// attributed to the enclosing scope's self time instead.
+    pub fn cancel(mut self) {
+        // Dropping the frame removes it without attributing its time
+        self.running = None;
+    }
+}
+
+impl<const W: usize, const A: usize, C: Clock> Drop for IterationGuard<'_, W, A, C> {
+    fn drop(&mut self) {
+        if let Some(Running {
+            start,
+            iterations,
+            frame,
+        }) = self.running.take()
+        {
+            self.timer
+                .record(frame, start, self.timer.clock.now(), iterations, false);
+        }
+    }
+}
@@ -0,0 +1,146 @@
+use std::sync::atomic::{AtomicU64, AtomicUsize, Ordering};
+
+/// Number of bits used for the linear sub-buckets within each power of two.
+/// Three bits gives eight sub-buckets per octave, i.e. a worst case relative
+/// error of 12.5% on any reported percentile.
+const SUB_BUCKET_BITS: u32 = 3;
+const SUB_BUCKETS: usize = 1 << SUB_BUCKET_BITS;
+const SUB_BUCKET_MASK: u64 = SUB_BUCKETS as u64 - 1;
+
+/// Total number of buckets required to cover every `u64` value.
+pub const NUM_BUCKETS: usize = (u64::BITS - SUB_BUCKET_BITS + 1) as usize * SUB_BUCKETS;
+
+/// A fixed-bucket log-linear histogram of iteration times (in nanoseconds).
+///
+/// Values below [SUB_BUCKETS] get their own bucket. Above that, every power of
+/// two is split into [SUB_BUCKETS] linearly spaced buckets. Recording is a
+/// single `fetch_add`, plus a `fetch_min`/`fetch_max` for the exact extremes
+/// whenever the value is a new one.
+pub struct Histogram {
+    buckets: [AtomicUsize; NUM_BUCKETS],
+    min: AtomicU64,
+    max: AtomicU64,
+}
+
+/// The latency distribution of a single window, derived from a [Histogram].
+///
+/// Percentiles are reported as the upper bound of the bucket they fall in,
+/// clamped to the exact `min` and `max` of the window.
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq)]
+pub struct Percentiles {
+    pub min: u64,
+    pub p50: u64,
+    pub p90: u64,
+    pub p99: u64,
+    pub max: u64,
+}
+
+impl Histogram {
+    /// Add one value to the histogram
+    pub fn record(&self, value: u64) {
+        // Windows synchronize their writers with whoever takes them, so
+        // nothing here needs to be ordered
+        self.buckets[bucket_index(value)].fetch_add(1, Ordering::Relaxed);
+        if value < self.min.load(Ordering::Relaxed) {
+            self.min.fetch_min(value, Ordering::Relaxed);
+        }
+        if value > self.max.load(Ordering::Relaxed) {
+            self.max.fetch_max(value, Ordering::Relaxed);
+        }
+    }
+
+    /// Move everything recorded in this histogram into `other`, resetting this
+    /// one.
+    pub fn drain_into(&self, other: &Histogram) {
+        for (bucket, other_bucket) in self.buckets.iter().zip(&other.buckets) {
+            let count = bucket.swap(0, Ordering::Relaxed);
+            if count > 0 {
+                other_bucket.fetch_add(count, Ordering::Relaxed);
+            }
+        }
+        other.min.fetch_min(
+            self.min.swap(u64::MAX, Ordering::Relaxed),
+            Ordering::Relaxed,
+        );
+        other
+            .max
+            .fetch_max(self.max.swap(0, Ordering::Relaxed), Ordering::Relaxed);
+    }
+
+    /// Reset the histogram, returning the distribution of everything recorded
+    /// since the last reset.
+    pub fn take(&self) -> Percentiles {
+        let mut counts = [0; NUM_BUCKETS];
+        let mut total: u64 = 0;
+        for (count, bucket) in counts.iter_mut().zip(&self.buckets) {
+            *count = bucket.swap(0, Ordering::Relaxed) as u64;
+            total = total.saturating_add(*count);
+        }
+        let min = self.min.swap(u64::MAX, Ordering::Relaxed);
+        let max = self.max.swap(0, Ordering::Relaxed);
+
+        if total == 0 {
+            return Percentiles::default();
+        }
+
+        let percentile = |p: u64| -> u64 {
+            // Rank (1-based) of the requested percentile
+            let rank = (total.saturating_mul(p)).div_ceil(100).max(1);
+            let mut seen: u64 = 0;
+            for (index, count) in counts.iter().enumerate() {
+                seen = seen.saturating_add(*count);
+                if seen >= rank {
+                    // Not `clamp`, which panics if a concurrent `record` made min > max
+                    return bucket_high(index).max(min).min(max);
+                }
+            }
+            max
+        };
+
+        Percentiles {
+            min,
+            p50: percentile(50),
+            p90: percentile(90),
+            p99: percentile(99),
+            max,
+        }
+    }
+}
+
+impl Default for Histogram {
+    fn default() -> Self {
+        Self {
+            buckets: std::array::from_fn(|_| AtomicUsize::new(0)),
+            min: AtomicU64::new(u64::MAX),
+            max: AtomicU64::new(0),
+        }
+    }
+}
+
+/// Index of the bucket containing `value`
+fn bucket_index(value: u64) -> usize {
+    if value < SUB_BUCKETS as u64 {
+        return value as usize;
+    }
+    let msb = u64::BITS - 1 - value.leading_zeros();
+    let shift = msb - SUB_BUCKET_BITS;
+    (((shift + 1) as usize) << SUB_BUCKET_BITS) + ((value >> shift) & SUB_BUCKET_MASK) as usize
+}
+
+/// Smallest value that lands in the bucket at `index`
+fn bucket_low(index: usize) -> u64 {
+    if index < SUB_BUCKETS {
+        return index as u64;
+    }
+    let shift = (index >> SUB_BUCKET_BITS) - 1;
+    (SUB_BUCKETS as u64 + (index as u64 & SUB_BUCKET_MASK)) << shift
+}
+
+/// Largest value that lands in the bucket at `index`
+fn bucket_high(index: usize) -> u64 {
+    if index + 1 == NUM_BUCKETS {
+        u64::MAX
+    } else {
+        bucket_low(index + 1) - 1
+    }
+}
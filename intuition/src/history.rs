@@ -0,0 +1,192 @@
+use std::{
+    hint,
+    sync::atomic::{fence, AtomicU64, AtomicUsize, Ordering},
+};
+
+use crate::{histogram::Percentiles, lap::MAX_LAPS, timer::WindowStats};
+
+/// Number of words of a [WindowStats] other than its laps and lap counts
+const FIXED_WORDS: usize = 15;
+/// Number of words a [WindowStats] occupies in a [History] slot.
+const WORDS: usize = FIXED_WORDS + 2 * MAX_LAPS;
+
+/// How many times a reader retries a slot that is mid-write before giving up on
+/// it for this read. Writers only hold a slot for a handful of stores, so this
+/// is only ever hit if the writing thread is descheduled.
+const MAX_READ_RETRIES: usize = 64;
+
+/// A fixed-capacity ring buffer holding the summaries of the most recent `A`
+/// windows.
+///
+/// Each completed window is pushed by exactly one writer (the thread that
+/// closed it), which claims a slot with a single `fetch_add` and never waits.
+/// Readers never block writers either: every slot is guarded by a sequence
+/// number (a seqlock), and a reader simply retries a slot it caught mid-write.
+pub struct History<const A: usize> {
+    /// Number of windows ever pushed
+    head: AtomicUsize,
+    slots: Box<[Slot]>,
+}
+
+struct Slot {
+    /// `2 * (index + 1)` once window `index` is fully written into this slot,
+    /// one less than that while it is being written.
+    seq: AtomicUsize,
+    words: [AtomicU64; WORDS],
+}
+
+impl<const A: usize> History<A> {
+    /// Append the summary of a completed window, overwriting the oldest one if
+    /// at capacity.
+    pub fn push(&self, stats: WindowStats) {
+        let index = self.head.fetch_add(1, Ordering::AcqRel);
+        let slot = &self.slots[index % A];
+        let seq = 2 * (index + 1);
+
+        // Mark the slot as being written before touching the data
+        slot.seq.store(seq - 1, Ordering::Relaxed);
+        fence(Ordering::Release);
+        for (word, value) in slot.words.iter().zip(to_words(&stats)) {
+            word.store(value, Ordering::Relaxed);
+        }
+        slot.seq.store(seq, Ordering::Release);
+    }
+
+    /// Number of windows pushed so far (including ones since overwritten)
+    pub fn len(&self) -> usize {
+        self.head.load(Ordering::Acquire)
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.len() == 0
+    }
+
+    /// Visit the `A` most recent windows, oldest first. Windows that have not
+    /// happened yet (or that could not be read consistently) are visited as
+    /// [WindowStats::default], so `f` is always called exactly `A` times.
+    pub fn read(&self, mut f: impl FnMut(WindowStats)) {
+        let head = self.len();
+        // Pad the front up to capacity while the history is still filling up
+        for _ in head..A {
+            f(WindowStats::default());
+        }
+        for index in head.saturating_sub(A)..head {
+            f(self.read_slot(index).unwrap_or_default());
+        }
+    }
+
+    /// Read window `index` from its slot, if it is (still) there
+    fn read_slot(&self, index: usize) -> Option<WindowStats> {
+        let slot = &self.slots[index % A];
+        let expected = 2 * (index + 1);
+        for _ in 0..MAX_READ_RETRIES {
+            let before = slot.seq.load(Ordering::Acquire);
+            if before > expected {
+                // Lapped by the writer; this window is gone
+                return None;
+            }
+            if before == expected {
+                let mut words = [0; WORDS];
+                for (value, word) in words.iter_mut().zip(&slot.words) {
+                    *value = word.load(Ordering::Relaxed);
+                }
+                fence(Ordering::Acquire);
+                if slot.seq.load(Ordering::Relaxed) == before {
+                    return Some(from_words(words));
+                }
+            }
+            // Mid-write (or not yet started by the claiming writer)
+            hint::spin_loop();
+        }
+        None
+    }
+}
+
+impl<const A: usize> Default for History<A> {
+    fn default() -> Self {
+        Self {
+            head: AtomicUsize::new(0),
+            slots: (0..A)
+                .map(|_| Slot {
+                    seq: AtomicUsize::new(0),
+                    words: Default::default(),
+                })
+                .collect(),
+        }
+    }
+}
+
+fn to_words(stats: &WindowStats) -> [u64; WORDS] {
+    let WindowStats {
+        count,
+        sampled,
+        total,
+        busy,
+        self_time,
+        failures,
+        failure_total,
+        laps,
+        lap_counts,
+        elapsed,
+        mean,
+        percentiles:
+            Percentiles {
+                min,
+                p50,
+                p90,
+                p99,
+                max,
+            },
+        partial,
+    } = *stats;
+    let mut words = [0; WORDS];
+    words[..FIXED_WORDS].copy_from_slice(&[
+        count as u64,
+        sampled as u64,
+        total,
+        busy,
+        self_time,
+        failures as u64,
+        failure_total,
+        elapsed,
+        mean,
+        min,
+        p50,
+        p90,
+        p99,
+        max,
+        partial as u64,
+    ]);
+    words[FIXED_WORDS..][..MAX_LAPS].copy_from_slice(&laps);
+    words[FIXED_WORDS + MAX_LAPS..].copy_from_slice(&lap_counts.map(|count| count as u64));
+    words
+}
+
+fn from_words(words: [u64; WORDS]) -> WindowStats {
+    let [count, sampled, total, busy, self_time, failures, failure_total, elapsed, mean, min, p50, p90, p99, max, partial]: [u64; FIXED_WORDS] =
+        words[..FIXED_WORDS].try_into().unwrap();
+    WindowStats {
+        count: count.try_into().unwrap_or(usize::MAX),
+        sampled: sampled.try_into().unwrap_or(usize::MAX),
+        total,
+        busy,
+        self_time,
+        failures: failures.try_into().unwrap_or(usize::MAX),
+        failure_total,
+        laps: words[FIXED_WORDS..][..MAX_LAPS].try_into().unwrap(),
+        lap_counts: std::array::from_fn(|lap| {
+            let count = words[FIXED_WORDS + MAX_LAPS + lap];
+            count.try_into().unwrap_or(usize::MAX)
+        }),
+        elapsed,
+        mean,
+        percentiles: Percentiles {
+            min,
+            p50,
+            p90,
+            p99,
+            max,
+        },
+        partial: partial != 0,
+    }
+}
@@ -0,0 +1,60 @@
+use std::{
+    future::Future,
+    pin::Pin,
+    task::{Context, Poll},
+};
+
+use crate::{clock::Clock, timer::Timer};
+
+/// A future that records itself as one iteration of a [Timer] when it
+/// completes. Created with [Timer::instrument].
+#[must_use = "futures do nothing unless polled"]
+pub struct Instrumented<'t, F, const W: usize, const A: usize, C: Clock> {
+    timer: &'t Timer<W, A, C>,
+    future: F,
+    /// Whether the timer was enabled when this future was created
+    enabled: bool,
+    /// When the future was first polled (according to the timer's clock), if
+    /// it has been
+    first_poll: Option<u64>,
+    /// Total time spent inside the inner future's `poll`, in nanoseconds
+    busy: u64,
+}
+
+impl<'t, F, const W: usize, const A: usize, C: Clock> Instrumented<'t, F, W, A, C> {
+    pub(crate) fn new(timer: &'t Timer<W, A, C>, future: F) -> Self {
+        Self {
+            timer,
+            enabled: timer.is_enabled(),
+            future,
+            first_poll: None,
+            busy: 0,
+        }
+    }
+}
+
+impl<F: Future, const W: usize, const A: usize, C: Clock> Future for Instrumented<'_, F, W, A, C> {
+    type Output = F::Output;
+
+    fn poll(self: Pin<&mut Self>, cx: &mut Context<'_>) -> Poll<Self::Output> {
+        // SAFETY: `future` is structurally pinned. It is never moved out of
+        // `self` (there is no `Drop` impl nor any accessor to it), and none of
+        // the other fields are pinned.
+        let this = unsafe { self.get_unchecked_mut() };
+        let future = unsafe { Pin::new_unchecked(&mut this.future) };
+        if !this.enabled {
+            return future.poll(cx);
+        }
+
+        let start = this.timer.clock.now();
+        let first_poll = *this.first_poll.get_or_insert(start);
+        let poll = future.poll(cx);
+        let end = this.timer.clock.now();
+        this.busy = this.busy.saturating_add(end.saturating_sub(start));
+
+        if poll.is_ready() {
+            this.timer.record_with_busy(first_poll, end, this.busy, 1);
+        }
+        poll
+    }
+}
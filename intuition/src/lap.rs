@@ -0,0 +1,87 @@
+//! Laps: the phases of a single iteration (see [crate::timer::Timer::iteration_laps]).
+
+#[cfg(not(feature = "disabled"))]
+use std::sync::OnceLock;
+
+#[cfg(not(feature = "disabled"))]
+use crate::clock::Clock;
+
+/// Maximum number of distinct laps per scope. Laps beyond that are not
+/// recorded (their time is still part of the iteration).
+pub const MAX_LAPS: usize = 8;
+
+/// Times the phases of an iteration, each ending at a call to [Laps::lap].
+#[cfg(not(feature = "disabled"))]
+pub struct Laps<'t, C> {
+    clock: &'t C,
+    names: &'t LapNames,
+    /// End of the previous lap (or start of the iteration), or `None` if the
+    /// iteration is not timed
+    last: Option<u64>,
+    /// Time spent in each lap so far, indexed like `names`
+    times: [u64; MAX_LAPS],
+    /// Whether each lap has ended yet
+    ended: [bool; MAX_LAPS],
+}
+
+#[cfg(not(feature = "disabled"))]
+impl<'t, C: Clock> Laps<'t, C> {
+    pub(crate) fn new(clock: &'t C, names: &'t LapNames, start: Option<u64>) -> Self {
+        Self {
+            clock,
+            names,
+            last: start,
+            times: [0; MAX_LAPS],
+            ended: [false; MAX_LAPS],
+        }
+    }
+
+    /// End the current lap, attributing the time since the previous lap (or
+    /// the start of the iteration) to `name`. A lap that ends several times
+    /// in one iteration gets the sum of those times.
+    pub fn lap(&mut self, name: &'static str) {
+        let Some(last) = self.last else {
+            return;
+        };
+        let now = self.clock.now();
+        if let Some(index) = self.names.index(name) {
+            self.times[index] = self.times[index].saturating_add(now.saturating_sub(last));
+            self.ended[index] = true;
+        }
+        self.last = Some(now);
+    }
+
+    pub(crate) fn times(&self) -> [u64; MAX_LAPS] {
+        self.times
+    }
+
+    /// 1 for each lap that ended in this iteration, 0 for the others
+    pub(crate) fn counts(&self) -> [usize; MAX_LAPS] {
+        self.ended.map(usize::from)
+    }
+}
+
+/// Names of the laps of a scope, in the order they were first seen
+#[cfg(not(feature = "disabled"))]
+#[derive(Default)]
+pub struct LapNames {
+    names: [OnceLock<&'static str>; MAX_LAPS],
+}
+
+#[cfg(not(feature = "disabled"))]
+impl LapNames {
+    /// Index of the lap called `name`, registering it if it is new. `None` if
+    /// all [MAX_LAPS] are taken by other names.
+    fn index(&self, name: &'static str) -> Option<usize> {
+        self.names.iter().position(|slot| {
+            // Either this thread registers the name here, or whoever did
+            // registered the same name
+            *slot.get_or_init(|| name) == name
+        })
+    }
+
+    /// Names of the laps seen so far
+    pub fn names(&self) -> impl Iterator<Item = &'static str> + '_ {
+        self.names.iter().map_while(|slot| slot.get().copied())
+    }
+}
@@ -1,7 +1,30 @@
 #![doc = include_str!("../README.md")]
 
+pub mod calibration;
+pub mod clock;
 pub mod dash;
+pub mod dynamic;
+#[cfg(not(feature = "disabled"))]
+pub mod guard;
+pub mod histogram;
+pub mod history;
+#[cfg(not(feature = "disabled"))]
+pub mod instrument;
+pub mod lap;
+#[cfg(feature = "log")]
+pub mod log_bridge;
+pub mod logs;
+#[cfg(not(feature = "disabled"))]
+mod nesting;
+#[cfg(feature = "disabled")]
+pub mod passthrough;
 pub mod profiler;
+#[cfg(not(feature = "disabled"))]
+pub mod shard;
 pub mod timer;
+#[cfg(feature = "tracing")]
+pub mod tracing_layer;
+#[cfg(not(feature = "disabled"))]
+pub mod window;
 
 pub use dash::Dash;
@@ -0,0 +1,132 @@
+//! Routing of the records of the [log] crate into the scopes of a profiler.
+//! Requires the `log` feature.
+
+use log::{Level, Metadata, Record, SetLoggerError};
+
+use crate::{
+    logs::{self, GLOBAL_SCOPE},
+    profiler::ProfilerExt,
+    timer::LogLevel,
+};
+
+/// A [log::Log] that logs every record to a scope of a profiler, so that the
+/// logs of your dependencies show up on the dashboard next to your own.
+///
+/// The scope is picked by the target of the record (by default its module
+/// path): the route with the longest matching target prefix wins, and
+/// records no route matches go to the fallback scope. Records for scopes the
+/// profiler does not have are dropped, as are records below the minimum level
+/// of their scope (see [crate::timer::Timer::set_min_level]).
+///
+/// ```rust, no_run
+/// use intuition::{construct_profiler, log_bridge::LogBridge};
+///
+/// construct_profiler!(MyProgramProfiler for MyProgram: global, network, part_1);
+/// static PROFILER: MyProgramProfiler<10, 10> = MyProgramProfiler::new();
+///
+/// LogBridge::from_profiler(&PROFILER)
+///     .route("hyper", "network")
+///     .route("reqwest", "network")
+///     .init()
+///     .unwrap();
+/// log::warn!(target: "hyper::client", "connection reset");
+/// ```
+pub struct LogBridge<P: 'static> {
+    profiler: &'static P,
+    /// Target prefixes and the names of the scopes their records go to
+    routes: Vec<(String, String)>,
+    fallback: String,
+}
+
+impl<P: ProfilerExt + Sync + 'static> LogBridge<P> {
+    /// Construct a bridge into a static profiler, like
+    /// [crate::Dash::from_profiler]
+    pub fn from_profiler(p: &'static impl ::core::ops::Deref<Target = P>) -> LogBridge<P> {
+        LogBridge::new(p.deref())
+    }
+
+    /// Construct a bridge into a static profiler that is not behind a
+    /// [Deref](core::ops::Deref), such as a [crate::dynamic::Profiler]
+    pub fn new(profiler: &'static P) -> LogBridge<P> {
+        LogBridge {
+            profiler,
+            routes: vec![],
+            fallback: GLOBAL_SCOPE.to_string(),
+        }
+    }
+
+    /// Log records whose target is `target` or starts with `target::` to
+    /// `scope`
+    pub fn route(mut self, target: &str, scope: &str) -> Self {
+        self.routes.push((target.to_string(), scope.to_string()));
+        self
+    }
+
+    /// Log records no route matches to `scope` instead of [GLOBAL_SCOPE]
+    pub fn fallback(mut self, scope: &str) -> Self {
+        self.fallback = scope.to_string();
+        self
+    }
+
+    /// Install the bridge as the logger of the [log] crate. The maximum level
+    /// of the [log] crate is set to [log::LevelFilter::Trace], so that
+    /// lowering the minimum level of a scope (e.g. from the dashboard) takes
+    /// effect right away.
+    pub fn init(self) -> Result<(), SetLoggerError> {
+        log::set_boxed_logger(Box::new(self))?;
+        log::set_max_level(log::LevelFilter::Trace);
+        Ok(())
+    }
+
+    /// Index of the scope records of `target` go to, if the profiler has it
+    fn scope(&self, target: &str) -> Option<usize> {
+        let scope = self
+            .routes
+            .iter()
+            .filter(|(prefix, _)| {
+                target
+                    .strip_prefix(prefix.as_str())
+                    .is_some_and(|rest| rest.is_empty() || rest.starts_with("::"))
+            })
+            .max_by_key(|(prefix, _)| prefix.len())
+            .map_or(&self.fallback, |(_, scope)| scope);
+        self.profiler.scope_index(scope)
+    }
+}
+
+impl<P: ProfilerExt + Sync + 'static> log::Log for LogBridge<P> {
+    fn enabled(&self, metadata: &Metadata) -> bool {
+        self.scope(metadata.target())
+            .is_some_and(|scope| self.profiler.log_enabled(scope, level(metadata.level())))
+    }
+
+    fn log(&self, record: &Record) {
+        let Some(scope) = self.scope(record.target()) else {
+            return;
+        };
+        let level = level(record.level());
+        if !self.profiler.log_enabled(scope, level) {
+            return;
+        }
+        self.profiler.log_at(
+            scope,
+            level,
+            logs::message(*record.args()),
+            record.file_static().unwrap_or("<unknown>"),
+            record.line().unwrap_or(0),
+        );
+    }
+
+    fn flush(&self) {}
+}
+
+/// The [LogLevel] of a [log::Level]
+fn level(level: Level) -> LogLevel {
+    match level {
+        Level::Error => LogLevel::Error,
+        Level::Warn => LogLevel::Warn,
+        Level::Info => LogLevel::Info,
+        Level::Debug => LogLevel::Debug,
+        Level::Trace => LogLevel::Trace,
+    }
+}
@@ -0,0 +1,382 @@
+//! Bounded storage of the logs of a scope, readable by any number of readers.
+
+use std::{
+    borrow::Cow,
+    collections::VecDeque,
+    fmt,
+    sync::{
+        atomic::{AtomicU64, Ordering},
+        Mutex,
+    },
+};
+
+use crate::timer::Log;
+
+/// Number of logs a scope holds by default; see [LogRing::set_capacity]
+pub const DEFAULT_CAPACITY: usize = 1024;
+
+/// Name of the scope that logs of other loggers go to by default if they
+/// cannot be attributed to a scope, e.g. by the `log` and `tracing` bridges
+pub const GLOBAL_SCOPE: &str = "global";
+
+/// Number of logs a [RecentLogs] keeps by default, which is more than fit in
+/// a log pane of the dashboard
+pub const DEFAULT_RECENT: usize = 100;
+
+/// The most recent logs of a scope, each numbered in the order it was logged
+/// ([Log::seq]). Once full, every new log drops the oldest one.
+///
+/// Reading does not remove anything, so any number of readers (e.g. the
+/// dashboard, an exporter and a test) can each follow the logs with their own
+/// [LogCursor].
+///
+/// A log identical to the newest one (same level, message and location) is
+/// not added again, but counted as a repeat of it ([Log::count]).
+pub struct LogRing {
+    inner: Mutex<Ring>,
+    limiter: RateLimiter,
+}
+
+struct Ring {
+    logs: VecDeque<Log>,
+    capacity: usize,
+    /// Sequence number of the next log
+    next: u64,
+}
+
+impl Ring {
+    /// Sequence number of the oldest log still held
+    fn first(&self) -> u64 {
+        self.next - self.logs.len() as u64
+    }
+}
+
+impl LogRing {
+    /// Add a log, numbering it and dropping the oldest one if full, unless it
+    /// repeats the newest one. Only new logs count towards the rate limit (see
+    /// [LogRing::set_rate_limit]), so that repeats are always counted in full.
+    pub fn push(&self, mut log: Log) {
+        let mut ring = self.inner.lock().unwrap();
+        if let Some(newest) = ring.logs.back_mut() {
+            if newest.repeated_by(&log) {
+                newest.count = newest.count.saturating_add(log.count);
+                newest.last_time = log.last_time;
+                newest.last_monotonic = log.last_monotonic;
+                return;
+            }
+        }
+        if !self.limiter.admit(log.monotonic) {
+            return;
+        }
+        log.seq = ring.next;
+        ring.next += 1;
+        if ring.logs.len() == ring.capacity {
+            ring.logs.pop_front();
+        }
+        if ring.capacity > 0 {
+            ring.logs.push_back(log);
+        }
+    }
+
+    /// Visit the logs `cursor` has not seen yet, oldest first, and move it
+    /// past them. Returns how many logs were dropped before the cursor got to
+    /// them (which are also added to [LogCursor::dropped]).
+    ///
+    /// The last log seen is visited again if it has been repeated since, so
+    /// readers keeping copies should replace the one with the same [Log::seq].
+    ///
+    /// The logs are copied out before `f` sees them, so `f` may log to this
+    /// ring (or panic) without blocking (or poisoning) it.
+    pub fn read(&self, cursor: &mut LogCursor, f: impl FnMut(&Log)) -> u64 {
+        let (unseen, dropped) = {
+            let ring = self.inner.lock().unwrap();
+            let first = ring.first();
+            let dropped = first.saturating_sub(cursor.next);
+            let skip = cursor.next.saturating_sub(first) as usize;
+            let repeated = skip
+                .checked_sub(1)
+                .and_then(|last| ring.logs.get(last))
+                .filter(|last_seen| last_seen.count > cursor.last_count);
+            let unseen: Vec<Log> = repeated
+                .into_iter()
+                .chain(ring.logs.iter().skip(skip))
+                .cloned()
+                .collect();
+            cursor.next = ring.next;
+            cursor.last_count = ring.logs.back().map_or(0, |newest| newest.count);
+            (unseen, dropped)
+        };
+        unseen.iter().for_each(f);
+        cursor.dropped += dropped;
+        dropped
+    }
+
+    /// Only admit logs at a sustained `limit.per_second`, with bursts of up to
+    /// `limit.burst` logs, or any number of logs if `None` (the default).
+    /// Logs beyond the limit are dropped and counted in
+    /// [LogRing::rate_limited]; the time is that of [Log::monotonic].
+    pub fn set_rate_limit(&self, limit: Option<RateLimit>) {
+        self.limiter.set(limit);
+    }
+
+    /// Number of logs not admitted because of the rate limit so far
+    pub fn rate_limited(&self) -> u64 {
+        self.limiter.limited.load(Ordering::Relaxed)
+    }
+
+    /// Copies of the logs currently held, oldest first
+    pub fn records(&self) -> Vec<Log> {
+        self.inner.lock().unwrap().logs.iter().cloned().collect()
+    }
+
+    /// Number of logs currently held
+    pub fn len(&self) -> usize {
+        self.inner.lock().unwrap().logs.len()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.len() == 0
+    }
+
+    /// Hold at most `capacity` logs, dropping the oldest ones if there are
+    /// more already. [DEFAULT_CAPACITY] by default.
+    pub fn set_capacity(&self, capacity: usize) {
+        let mut ring = self.inner.lock().unwrap();
+        let excess = ring.logs.len().saturating_sub(capacity);
+        ring.logs.drain(..excess);
+        ring.capacity = capacity;
+    }
+}
+
+impl Default for LogRing {
+    fn default() -> Self {
+        Self {
+            inner: Mutex::new(Ring {
+                logs: VecDeque::new(),
+                capacity: DEFAULT_CAPACITY,
+                next: 0,
+            }),
+            limiter: RateLimiter::default(),
+        }
+    }
+}
+
+/// Position of one reader in a [LogRing]. A new cursor starts at the oldest
+/// log still held.
+#[derive(Clone, Debug, Default)]
+pub struct LogCursor {
+    /// Sequence number of the next log to read
+    next: u64,
+    /// [Log::count] of the last log read, when it was read
+    last_count: u64,
+    /// Number of logs dropped before this reader got to them, in total
+    pub dropped: u64,
+}
+
+/// The last logs of a scope read by one reader, e.g. for display
+pub struct RecentLogs {
+    pub cursor: LogCursor,
+    /// At most `capacity` logs, oldest first
+    pub logs: Vec<Log>,
+    /// Number of logs of the scope discarded by its rate limit; see
+    /// [LogRing::set_rate_limit]
+    pub rate_limited: u64,
+    capacity: usize,
+}
+
+impl RecentLogs {
+    /// Keep the last `capacity` logs read
+    pub fn new(capacity: usize) -> Self {
+        Self {
+            cursor: LogCursor::default(),
+            logs: vec![],
+            rate_limited: 0,
+            capacity,
+        }
+    }
+
+    /// Read the logs added to `ring` since the last update
+    pub fn update(&mut self, ring: &LogRing) {
+        let logs = &mut self.logs;
+        ring.read(&mut self.cursor, |log| match logs.last_mut() {
+            // Repeated since it was last read
+            Some(last) if last.seq == log.seq => *last = log.clone(),
+            _ => logs.push(log.clone()),
+        });
+        self.rate_limited = ring.rate_limited();
+        let excess = self.logs.len().saturating_sub(self.capacity);
+        self.logs.drain(..excess);
+    }
+}
+
+impl Default for RecentLogs {
+    fn default() -> Self {
+        Self::new(DEFAULT_RECENT)
+    }
+}
+
+/// A limit on the rate of logs of a scope; see [LogRing::set_rate_limit]
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub struct RateLimit {
+    /// Sustained number of logs per second
+    pub per_second: u64,
+    /// Number of logs admitted at once after a quiet period
+    pub burst: u64,
+}
+
+/// A token bucket, implemented as the equivalent generic cell rate algorithm
+/// so that admitting a log is a single compare-and-swap
+#[derive(Default)]
+struct RateLimiter {
+    /// Nanoseconds between logs at the sustained rate, or 0 if unlimited
+    interval: AtomicU64,
+    /// How far ahead of now `due` may be, i.e. the size of the bucket
+    tolerance: AtomicU64,
+    /// When the bucket will be full again if nothing else is logged
+    due: AtomicU64,
+    /// Number of logs not admitted
+    limited: AtomicU64,
+}
+
+impl RateLimiter {
+    fn set(&self, limit: Option<RateLimit>) {
+        let (interval, tolerance) = limit.map_or((0, 0), |limit| {
+            let interval = 1_000_000_000 / limit.per_second.max(1);
+            (interval, interval.saturating_mul(limit.burst.max(1) - 1))
+        });
+        self.tolerance.store(tolerance, Ordering::Relaxed);
+        self.interval.store(interval, Ordering::Relaxed);
+    }
+
+    #[inline]
+    fn admit(&self, now: u64) -> bool {
+        let interval = self.interval.load(Ordering::Relaxed);
+        if interval == 0 {
+            return true;
+        }
+        let tolerance = self.tolerance.load(Ordering::Relaxed);
+        let admitted = self
+            .due
+            .fetch_update(Ordering::Relaxed, Ordering::Relaxed, |due| {
+                let due = due.max(now);
+                (due - now <= tolerance).then(|| due.saturating_add(interval))
+            })
+            .is_ok();
+        if !admitted {
+            self.limited.fetch_add(1, Ordering::Relaxed);
+        }
+        admitted
+    }
+}
+
+/// The message of a formatted log, borrowed rather than copied if it has no
+/// arguments. Used by the logging macros and the `log` bridge.
+pub fn message(args: fmt::Arguments<'_>) -> Cow<'static, str> {
+    match args.as_str() {
+        Some(log) => Cow::Borrowed(log),
+        None => Cow::Owned(args.to_string()),
+    }
+}
+
+/// Log to a [Timer](crate::timer::Timer) (or a scope of a profiler) at a given
+/// [LogLevel](crate::timer::LogLevel), recording where it was called from.
+///
+/// The message is formatted like with [format!], but only if the level is
+/// enabled (see [Timer::log_enabled](crate::timer::Timer::log_enabled)), so
+/// filtered logs cost next to nothing. Structured `key = value` fields may
+/// precede the message, each followed by a comma; their values are formatted
+/// with [Display](std::fmt::Display) and stored in
+/// [Log::fields](crate::timer::Log::fields).
+///
+/// ```rust
+/// # use intuition::{construct_profiler, timer::LogLevel};
+/// construct_profiler!(MyProgramProfiler for MyProgram: queue);
+/// static PROFILER: MyProgramProfiler<10, 10> = MyProgramProfiler::new();
+///
+/// let (name, n) = ("jobs", 64);
+/// intuition::log!(PROFILER.queue, LogLevel::Warn, "queue {name} full");
+/// intuition::log!(PROFILER.queue, LogLevel::Warn, len = n, "queue {} full", name);
+/// ```
+///
+/// The shorthands [trace!](crate::trace!), [debug!](crate::debug!),
+/// [info!](crate::info!), [warn!](crate::warn!) and [error!](crate::error!)
+/// omit the level.
+#[macro_export]
+macro_rules! log {
+    ($timer:expr, $level:expr, $fmt:literal $(, $($arg:tt)*)?) => {{
+        let timer = &$timer;
+        let level = $level;
+        if timer.log_enabled(level) {
+            timer.log_at(
+                level,
+                $crate::logs::message(::std::format_args!($fmt $(, $($arg)*)?)),
+                ::std::file!(),
+                ::std::line!(),
+            );
+        }
+    }};
+    ($timer:expr, $level:expr, $($key:ident = $value:expr,)+ $fmt:literal $(, $($arg:tt)*)?) => {{
+        let timer = &$timer;
+        let level = $level;
+        if timer.log_enabled(level) {
+            timer.log_fields_at(
+                level,
+                $crate::logs::message(::std::format_args!($fmt $(, $($arg)*)?)),
+                ::std::vec![$((
+                    ::std::stringify!($key),
+                    ::std::string::ToString::to_string(&$value),
+                )),+],
+                ::std::file!(),
+                ::std::line!(),
+            );
+        }
+    }};
+}
+
+/// Log at [LogLevel::Trace](crate::timer::LogLevel::Trace); see [log!](crate::log!)
+#[macro_export]
+macro_rules! trace {
+    ($timer:expr, $($arg:tt)+) => {
+        $crate::log!($timer, $crate::timer::LogLevel::Trace, $($arg)+)
+    };
+}
+
+/// Log at [LogLevel::Debug](crate::timer::LogLevel::Debug); see [log!](crate::log!)
+#[macro_export]
+macro_rules! debug {
+    ($timer:expr, $($arg:tt)+) => {
+        $crate::log!($timer, $crate::timer::LogLevel::Debug, $($arg)+)
+    };
+}
+
+/// Log at [LogLevel::Info](crate::timer::LogLevel::Info); see [log!](crate::log!)
+#[macro_export]
+macro_rules! info {
+    ($timer:expr, $($arg:tt)+) => {
+        $crate::log!($timer, $crate::timer::LogLevel::Info, $($arg)+)
+    };
+}
+
+/// Log at [LogLevel::Warn](crate::timer::LogLevel::Warn); see [log!](crate::log!)
+///
+/// ```rust
+/// # use intuition::construct_profiler;
+/// # construct_profiler!(MyProgramProfiler for MyProgram: queue);
+/// # static PROFILER: MyProgramProfiler<10, 10> = MyProgramProfiler::new();
+/// # let n = 64;
+/// intuition::warn!(PROFILER.queue, "queue {} full", n);
+/// ```
+#[macro_export]
+macro_rules! warn {
+    ($timer:expr, $($arg:tt)+) => {
+        $crate::log!($timer, $crate::timer::LogLevel::Warn, $($arg)+)
+    };
+}
+
+/// Log at [LogLevel::Error](crate::timer::LogLevel::Error); see [log!](crate::log!)
+#[macro_export]
+macro_rules! error {
+    ($timer:expr, $($arg:tt)+) => {
+        $crate::log!($timer, $crate::timer::LogLevel::Error, $($arg)+)
+    };
+}
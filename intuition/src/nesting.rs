@@ -0,0 +1,87 @@
+//! Per-thread tracking of nested scopes.
+//!
+//! Every synchronous iteration ([crate::timer::Timer::iteration] or
+//! [crate::timer::Timer::start]) pushes a frame onto a thread-local stack when
+//! it starts and removes it when it ends. A frame accumulates the time of the
+//! iterations that completed while it was on top, which is how a scope knows
+//! how much of its time was spent in its children, and therefore its self
+//! time.
+//!
+//! Nesting is purely dynamic: a scope is a child of whatever scope is running
+//! on the same thread when it starts, regardless of how the scopes were
+//! declared in [crate::construct_profiler]. Iterations normally end in the
+//! reverse order they started, which is always the case for closures and for
+//! guards dropped at the end of their block. An iteration that ends out of
+//! order (e.g. guards held across `.await` by tasks sharing a thread) only
+//! removes its own frame, and its time goes to the frame below it.
+//!
+//! A [Frame] cannot leave the thread that pushed it, and removes itself
+//! without attributing any time if it is dropped instead of exited, e.g. when
+//! the iteration panics.
+
+use std::{
+    cell::{Cell, RefCell},
+    marker::PhantomData,
+    mem,
+};
+
+thread_local! {
+    /// Id of every running scope, with the time (in nanoseconds) spent in its
+    /// completed children
+    static FRAMES: RefCell<Vec<(u64, u64)>> = const { RefCell::new(Vec::new()) };
+    /// Id of the next frame pushed on this thread
+    static NEXT_ID: Cell<u64> = const { Cell::new(0) };
+}
+
+/// A running (possibly nested) iteration on this thread
+pub(crate) struct Frame {
+    id: u64,
+    /// Frames live on the stack of the thread that entered them
+    _thread: PhantomData<*const ()>,
+}
+
+/// Start a (possibly nested) iteration on this thread
+pub(crate) fn enter() -> Frame {
+    let id = NEXT_ID.with(|next| next.replace(next.get().wrapping_add(1)));
+    FRAMES.with(|frames| frames.borrow_mut().push((id, 0)));
+    Frame {
+        id,
+        _thread: PhantomData,
+    }
+}
+
+impl Frame {
+    /// End the iteration, which took `time` nanoseconds, returning the time
+    /// spent in its children.
+    pub(crate) fn exit(self, time: u64) -> u64 {
+        let children = remove(self.id, Some(time));
+        mem::forget(self);
+        children
+    }
+}
+
+impl Drop for Frame {
+    /// End the iteration without attributing its time to the enclosing one
+    fn drop(&mut self) {
+        remove(self.id, None);
+    }
+}
+
+/// Remove frame `id` from this thread's stack, adding `time` to the frame
+/// below it, and return the time spent in its children
+fn remove(id: u64, time: Option<u64>) -> u64 {
+    FRAMES
+        .try_with(|frames| {
+            let mut frames = frames.borrow_mut();
+            let Some(index) = frames.iter().rposition(|&(frame, _)| frame == id) else {
+                return 0;
+            };
+            let (_, children) = frames.remove(index);
+            if let (Some(time), Some(parent)) = (time, index.checked_sub(1)) {
+                frames[parent].1 = frames[parent].1.saturating_add(time);
+            }
+            children
+        })
+        // The thread is exiting
+        .unwrap_or(0)
+}
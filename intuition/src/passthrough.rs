@@ -0,0 +1,239 @@
+//! What [Timer] and the types it hands out compile to with the `disabled`
+//! feature: zero-sized passthroughs with the same methods, which run
+//! iterations without reading any clock, and record and log nothing.
+//!
+//! Only the methods are kept; code reading the fields of a timer (e.g.
+//! `total_count` or `logs`) has to be compiled out along with it.
+
+use std::{
+    borrow::Cow,
+    fmt::Display,
+    future::Future,
+    marker::PhantomData,
+    pin::Pin,
+    task::{Context, Poll},
+    time::Duration,
+};
+
+use crate::{
+    clock::StdClock,
+    logs::{RateLimit, RecentLogs},
+    profiler::{ScopeExt, StateBuffer},
+    timer::{LogLevel, Sampling, WindowStats},
+};
+
+/// A scope that times nothing; see the [module docs](self)
+pub struct Timer<const W: usize, const A: usize, C = StdClock>(PhantomData<fn() -> C>);
+
+impl<const W: usize, const A: usize, C> Timer<W, A, C> {
+    /// The clock is dropped right away
+    pub fn with_clock(_clock: C) -> Self {
+        Self(PhantomData)
+    }
+
+    #[inline(always)]
+    pub fn iteration<T, F: FnOnce() -> T>(&self, iter: F) -> T {
+        iter()
+    }
+
+    #[inline(always)]
+    pub fn iteration_result<T, E, F>(&self, iter: F) -> Result<T, E>
+    where
+        E: Display,
+        F: FnOnce() -> Result<T, E>,
+    {
+        iter()
+    }
+
+    #[inline(always)]
+    pub fn iteration_laps<T, F: FnOnce(&mut Laps<'_, C>) -> T>(&self, iter: F) -> T {
+        iter(&mut Laps(PhantomData))
+    }
+
+    /// Leaves `buffer` empty, as there are no laps
+    pub fn update_lap_buffer(&self, buffer: &mut StateBuffer) {
+        buffer.clear();
+    }
+
+    pub fn set_log_errors(&self, _log_errors: bool) {}
+
+    #[inline(always)]
+    pub fn start(&self) -> IterationGuard<'_, W, A, C> {
+        IterationGuard(PhantomData)
+    }
+
+    #[inline(always)]
+    pub fn instrument<F: Future>(&self, future: F) -> Instrumented<'_, F, W, A, C> {
+        Instrumented {
+            future,
+            timer: PhantomData,
+        }
+    }
+
+    pub fn set_enabled(&self, _enabled: bool) {}
+
+    #[inline(always)]
+    pub fn is_enabled(&self) -> bool {
+        false
+    }
+
+    #[inline(always)]
+    pub fn total_count(&self) -> usize {
+        0
+    }
+
+    pub fn set_sampling(&self, _sampling: Sampling) {}
+
+    pub fn sampling(&self) -> Sampling {
+        Sampling::All
+    }
+
+    pub fn record_iteration(&self, _start: u64, _end: u64, _busy: u64) {}
+
+    pub fn set_window_interval(&self, _interval: Option<Duration>) {}
+
+    pub fn close_elapsed_windows(&self) {}
+
+    pub fn flush(&self) {}
+
+    pub fn set_flush_after(&self, _after: Option<Duration>) {}
+
+    pub fn enable_sharding(&self, _shards: usize) {}
+
+    pub fn set_min_level(&self, _level: LogLevel) {}
+
+    pub fn min_level(&self) -> LogLevel {
+        LogLevel::default()
+    }
+
+    pub fn set_log_rate_limit(&self, _limit: Option<RateLimit>) {}
+
+    #[inline(always)]
+    pub fn log_enabled(&self, _level: LogLevel) -> bool {
+        false
+    }
+
+    pub fn trace<L: Into<Cow<'static, str>>>(&self, _log: L) {}
+
+    pub fn debug<L: Into<Cow<'static, str>>>(&self, _log: L) {}
+
+    pub fn info<L: Into<Cow<'static, str>>>(&self, _log: L) {}
+
+    pub fn error<L: Into<Cow<'static, str>>>(&self, _log: L) {}
+
+    pub fn warn<L: Into<Cow<'static, str>>>(&self, _log: L) {}
+
+    pub fn log_at<L: Into<Cow<'static, str>>>(
+        &self,
+        _level: LogLevel,
+        _log: L,
+        _file: &'static str,
+        _line: u32,
+    ) {
+    }
+
+    pub fn log_fields_at<L: Into<Cow<'static, str>>>(
+        &self,
+        _level: LogLevel,
+        _log: L,
+        _fields: Vec<(&'static str, String)>,
+        _file: &'static str,
+        _line: u32,
+    ) {
+    }
+}
+
+impl<const W: usize, const A: usize, C> Default for Timer<W, A, C> {
+    fn default() -> Self {
+        Self(PhantomData)
+    }
+}
+
+impl<const W: usize, const A: usize, C> ScopeExt for Timer<W, A, C> {
+    fn read_windows(&self, _f: &mut dyn FnMut(WindowStats)) {}
+
+    fn update_lap_buffer(&self, buffer: &mut StateBuffer) {
+        Timer::update_lap_buffer(self, buffer);
+    }
+
+    fn update_logs(&self, _logs: &mut RecentLogs) {}
+
+    fn sampling(&self) -> Sampling {
+        Sampling::All
+    }
+
+    fn min_level(&self) -> LogLevel {
+        LogLevel::default()
+    }
+
+    fn set_min_level(&self, _level: LogLevel) {}
+
+    fn log_enabled(&self, _level: LogLevel) -> bool {
+        false
+    }
+
+    fn log_fields_at(
+        &self,
+        _level: LogLevel,
+        _log: Cow<'static, str>,
+        _fields: Vec<(&'static str, String)>,
+        _file: &'static str,
+        _line: u32,
+    ) {
+    }
+
+    fn now(&self) -> u64 {
+        0
+    }
+
+    fn record_iteration(&self, _start: u64, _end: u64, _busy: u64) {}
+
+    /// Nothing is timed, so there is no overhead (and no calibrating it)
+    fn overhead(&self) -> Duration {
+        Duration::ZERO
+    }
+
+    fn close_elapsed_windows(&self) {}
+
+    fn flush(&self) {}
+}
+
+/// Returned by [Timer::start]. Like the guard it stands in for, it cannot
+/// leave the thread that created it.
+#[must_use = "the iteration ends as soon as the guard is dropped"]
+pub struct IterationGuard<'t, const W: usize, const A: usize, C>(
+    PhantomData<(&'t Timer<W, A, C>, *const ())>,
+);
+
+impl<const W: usize, const A: usize, C> IterationGuard<'_, W, A, C> {
+    pub fn finish(self) -> Duration {
+        Duration::ZERO
+    }
+
+    pub fn cancel(self) {}
+}
+
+/// Returned by [Timer::instrument]; polls the future and nothing else
+#[must_use = "futures do nothing unless polled"]
+pub struct Instrumented<'t, F, const W: usize, const A: usize, C> {
+    future: F,
+    timer: PhantomData<&'t Timer<W, A, C>>,
+}
+
+impl<F: Future, const W: usize, const A: usize, C> Future for Instrumented<'_, F, W, A, C> {
+    type Output = F::Output;
+
+    #[inline(always)]
+    fn poll(self: Pin<&mut Self>, cx: &mut Context<'_>) -> Poll<Self::Output> {
+        // SAFETY: `future` is structurally pinned, as in the real Instrumented
+        unsafe { self.map_unchecked_mut(|this| &mut this.future) }.poll(cx)
+    }
+}
+
+/// Handed to the closure of [Timer::iteration_laps]
+pub struct Laps<'t, C>(PhantomData<&'t C>);
+
+impl<C> Laps<'_, C> {
+    #[inline(always)]
+    pub fn lap(&mut self, _name: &'static str) {}
+}
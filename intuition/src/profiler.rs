@@ -1,51 +1,121 @@
-use super::timer::Log;
+use std::{borrow::Cow, time::Duration};
+
+#[cfg(not(feature = "disabled"))]
+use super::{clock::Clock, timer::Timer};
+use super::{
+    logs::RecentLogs,
+    timer::{LogLevel, Sampling, Statistic, WindowStats},
+};
 pub use concat_idents::concat_idents as ci;
-pub use once_cell::sync::Lazy;
+pub use once_cell::sync::{Lazy, OnceCell};
 
+/// Construct a profiler type with one [crate::timer::Timer] per scope.
+///
+/// The profiler type takes the [crate::clock::Clock] of its timers as an
+/// optional third parameter, e.g. `MyProfiler<10, 10, TscClock>`. All scopes
+/// of a profiler share one clock: `MyProfiler::new()` makes it with
+/// `Default` (the first time the profiler is used), and
+/// `MyProfiler::with_clock(clock)` hands clones of `clock` to every scope,
+/// e.g. of an `Arc<MockClock>` driving the whole profiler in tests.
+///
+/// Scopes can be nested by listing children in braces after their parent,
+/// e.g. `construct_profiler!(HashSum: generator, hasher { finalize, send })`.
+/// Every scope (nested or not) is still a field of the profiler
+/// (`PROFILER.finalize`), so scope names must be unique. The declared tree is
+/// only used to display the scopes; self times are computed from how
+/// iterations actually nest at runtime.
 #[macro_export(local_inner_macros)]
 macro_rules! construct_profiler {
-    ($title:ident: $( $scope:ident ),*) => {
-        construct_profiler!(Profiler for $title: $($scope),*);
+    // Flatten the scope tree into a list of `scope (depth);`. Each pending
+    // `(depth) { scopes }` frame holds the siblings left to visit at a depth.
+    (@flatten $name:ident $title:ident [$($acc:tt)*] ($depth:expr) { $scope:ident { $($children:tt)* } $(, $($rest:tt)*)? } $($stack:tt)*) => {
+        construct_profiler!(@flatten $name $title [$($acc)* $scope ($depth);] ($depth + 1) { $($children)* } ($depth) { $($($rest)*)? } $($stack)*);
+    };
+    (@flatten $name:ident $title:ident [$($acc:tt)*] ($depth:expr) { $scope:ident $(, $($rest:tt)*)? } $($stack:tt)*) => {
+        construct_profiler!(@flatten $name $title [$($acc)* $scope ($depth);] ($depth) { $($($rest)*)? } $($stack)*);
+    };
+    (@flatten $name:ident $title:ident [$($acc:tt)*] ($depth:expr) {} $($stack:tt)*) => {
+        construct_profiler!(@flatten $name $title [$($acc)*] $($stack)*);
+    };
+    (@flatten $name:ident $title:ident [$($acc:tt)*]) => {
+        construct_profiler!(@generate $name $title [$($acc)*]);
+    };
+
+    ($title:ident: $($scopes:tt)*) => {
+        construct_profiler!(Profiler for $title: $($scopes)*);
+    };
+
+    ($name:ident for $title:ident: $($scopes:tt)*) => {
+        construct_profiler!(@flatten $name $title [] (0) { $($scopes)* });
     };
 
-    ($name:ident for $title:ident: $( $scope:ident ),*) => {
+    (@generate $name:ident $title:ident [$( $scope:ident ($depth:expr); )*]) => {
 
         use __inner_profiler_module::$name;
         mod __inner_profiler_module {
 
         $crate::profiler::ci!(inner = $name, Inner {
 
-            pub(crate) struct $name<const W: usize, const A: usize>(intuition::profiler::Lazy<inner<W,A>>);
+            pub(crate) struct $name<const W: usize, const A: usize, C = $crate::clock::StdClock>($crate::profiler::OnceCell<inner<W,A,C>>);
 
-            impl<const W: usize, const A: usize> $name<W,A> {
+            impl<const W: usize, const A: usize, C: $crate::clock::Clock> $name<W,A,C> {
+                /// A profiler whose scopes share a clock made with
+                /// `C::default()` on first use
                 pub const fn new() -> Self {
-                    Self(intuition::profiler::Lazy::new(inner::new))
+                    Self($crate::profiler::OnceCell::new())
+                }
+
+                /// A profiler whose scopes all read the time from `clock`
+                /// (or a clone of it, e.g. an `Arc<MockClock>`)
+                pub fn with_clock(clock: C) -> Self
+                where
+                    C: Clone,
+                {
+                    Self($crate::profiler::OnceCell::with_value(inner::with_clock(clock)))
                 }
             }
 
-            impl<const W: usize, const A: usize> std::ops::Deref for $name<W,A> {
-                type Target = inner<W, A>;
+            impl<const W: usize, const A: usize, C: $crate::clock::Clock + Default + Clone> std::ops::Deref for $name<W,A,C> {
+                type Target = inner<W, A, C>;
                 fn deref(&self) -> &Self::Target {
-                    self.0.deref()
+                    self.0.get_or_init(inner::new)
                 }
             }
 
-            #[derive(Default)]
-            pub(crate) struct inner<const W: usize, const A: usize> {
+            pub(crate) struct inner<const W: usize, const A: usize, C = $crate::clock::StdClock> {
                 $(
-                    pub(crate) $scope: $crate::timer::Timer<W,A>,
+                    pub(crate) $scope: $crate::timer::Timer<W,A,C>,
                 )*
             }
 
-            impl<const W: usize, const A: usize> inner<W,A> {
-                pub(crate) fn new() -> Self {
+            impl<const W: usize, const A: usize, C: $crate::clock::Clock + Clone> inner<W,A,C> {
+                pub(crate) fn new() -> Self
+                where
+                    C: Default,
+                {
                     inner::default()
                 }
 
+                /// Every scope gets a clone of `clock`, so that all times of
+                /// the profiler are comparable
+                pub(crate) fn with_clock(clock: C) -> Self {
+                    Self {
+                        $(
+                            $scope: $crate::timer::Timer::with_clock(clock.clone()),
+                        )*
+                    }
+                }
+            }
+
+            impl<const W: usize, const A: usize, C: $crate::clock::Clock + Default + Clone> Default for inner<W,A,C> {
+                fn default() -> Self {
+                    inner::with_clock(C::default())
+                }
             }
 
-            impl<const W: usize, const A: usize> $crate::profiler::ProfilerExt for inner<W,A> {
+            impl<const W: usize, const A: usize, C: $crate::clock::Clock + Default> $crate::profiler::ProfilerExt for inner<W,A,C> {
                 const SCOPES: &'static [&'static str] = &[$(std::stringify!($scope),)*];
+                const DEPTHS: &'static [usize] = &[$($depth,)*];
                 const TITLE: &'static str = std::stringify!($title);
                 const WINDOW_SIZE: usize = W;
                 const NUM_AVERAGES: usize = A;
@@ -61,7 +131,7 @@ macro_rules! construct_profiler {
                 fn log_buffer(&self) -> $crate::profiler::LogBuffer {
                     std::vec![
                         $(
-                            (std::stringify!($scope), std::vec![]),
+                            (std::stringify!($scope), $crate::logs::RecentLogs::default()),
                         )*
                         ]
                     }
@@ -79,12 +149,27 @@ macro_rules! construct_profiler {
                             // Ensure we are updating proper scope
                             std::assert_eq!(scope_name, &std::stringify!($scope));
                             std::assert_eq!(recent_averages.capacity(), A);
-                            self.$scope.recent_averages.lock().unwrap().clone_into(recent_averages);
+                            recent_averages.clear();
+                            $crate::profiler::ScopeExt::read_windows(&self.$scope, &mut |window| recent_averages.push(window.mean));
                             i += 1;
                         )*
                     }
                 }
 
+                fn scope(&self, index: usize) -> Option<&dyn $crate::profiler::ScopeExt> {
+                    let mut i = 0;
+                    #[allow(unused_assignments)] // i is incremented on the last scope as well
+                    {
+                        $(
+                            if index == i {
+                                return Some(&self.$scope);
+                            }
+                            i += 1;
+                        )*
+                    }
+                    None
+                }
+
                 fn update_logs(&self, buffer: &mut $crate::profiler::LogBuffer) {
 
                     std::assert_eq!(buffer.len(), Self::SCOPES.len());
@@ -97,8 +182,8 @@ macro_rules! construct_profiler {
                             let (scope_name, logs) = &mut buffer[i];
                             // Ensure we are updating proper scope
                             std::assert_eq!(scope_name, &std::stringify!($scope));
-                            // Take current logs
-                            logs.append(&mut std::mem::take(&mut *self.$scope.logs.lock().unwrap()));
+                            // Read new logs
+                            $crate::profiler::ScopeExt::update_logs(&self.$scope, logs);
                             i += 1;
                         )*
                     }
@@ -109,16 +194,273 @@ macro_rules! construct_profiler {
         };
     }
 
-pub type StateBuffer = Vec<(&'static str, Vec<usize>)>;
-pub type LogBuffer = Vec<(&'static str, Vec<Log>)>;
+pub type StateBuffer = Vec<(&'static str, Vec<u64>)>;
+pub type LogBuffer = Vec<(&'static str, RecentLogs)>;
+pub type PartialBuffer = Vec<(&'static str, Vec<bool>)>;
+/// The average time of each lap of each scope in recent windows
+pub type LapBuffer = Vec<(&'static str, StateBuffer)>;
 
 pub trait ProfilerExt {
     const SCOPES: &'static [&'static str];
+    /// How deeply each of [ProfilerExt::SCOPES] is nested in the declared
+    /// scope tree (0 for top-level scopes)
+    const DEPTHS: &'static [usize] = &[];
     const TITLE: &'static str;
     const WINDOW_SIZE: usize;
     const NUM_AVERAGES: usize;
+    /// Title of the profiler. Defaults to [ProfilerExt::TITLE], but may be
+    /// decided at runtime.
+    fn title(&self) -> &'static str {
+        Self::TITLE
+    }
+    /// How deeply the scope at `index` is nested. Defaults to
+    /// [ProfilerExt::DEPTHS], but may be decided at runtime.
+    fn depth(&self, index: usize) -> usize {
+        Self::DEPTHS.get(index).copied().unwrap_or(0)
+    }
     fn update_logs(&self, buffer: &mut LogBuffer);
     fn update_buffer(&self, buffer: &mut StateBuffer);
     fn state_buffer(&self) -> StateBuffer;
     fn log_buffer(&self) -> LogBuffer;
+    /// The scope at `index`, if there is one. Most of the methods below are
+    /// implemented in terms of this; without it (the default) they do
+    /// nothing.
+    fn scope(&self, _index: usize) -> Option<&dyn ScopeExt> {
+        None
+    }
+    /// Like [ProfilerExt::update_buffer], but fills the buffer with the given
+    /// [Statistic] of each recent window instead of its mean.
+    fn update_statistic_buffer(&self, statistic: Statistic, buffer: &mut StateBuffer) {
+        for (index, (_, values)) in buffer.iter_mut().enumerate() {
+            values.clear();
+            if let Some(scope) = self.scope(index) {
+                scope.read_windows(&mut |window| values.push(statistic.of(&window)));
+            }
+        }
+    }
+    /// Fill the buffer with the throughput (completed iterations per second)
+    /// of each recent window.
+    fn update_throughput_buffer(&self, buffer: &mut StateBuffer) {
+        for (index, (_, values)) in buffer.iter_mut().enumerate() {
+            values.clear();
+            if let Some(scope) = self.scope(index) {
+                scope.read_windows(&mut |window| values.push(window.throughput()));
+            }
+        }
+    }
+    /// Fill the buffer with whether each recent window is
+    /// [crate::timer::WindowStats::partial]. Like the other buffers, it must
+    /// have an entry per scope.
+    fn update_partial_buffer(&self, buffer: &mut PartialBuffer) {
+        for (index, (_, partial)) in buffer.iter_mut().enumerate() {
+            partial.clear();
+            if let Some(scope) = self.scope(index) {
+                scope.read_windows(&mut |window| partial.push(window.partial));
+            }
+        }
+    }
+    /// Fill the buffer with the laps of each scope; see
+    /// [crate::timer::Timer::update_lap_buffer]
+    fn update_lap_buffer(&self, buffer: &mut LapBuffer) {
+        for (index, (_, laps)) in buffer.iter_mut().enumerate() {
+            if let Some(scope) = self.scope(index) {
+                scope.update_lap_buffer(laps);
+            }
+        }
+    }
+    /// Which iterations of the scope at `index` are timed; see
+    /// [crate::timer::Timer::set_sampling]
+    fn sampling(&self, index: usize) -> Sampling {
+        self.scope(index)
+            .map(|scope| scope.sampling())
+            .unwrap_or_default()
+    }
+    /// Least severe level logged by the scope at `index`; see
+    /// [crate::timer::Timer::set_min_level]
+    fn min_level(&self, index: usize) -> LogLevel {
+        self.scope(index)
+            .map(|scope| scope.min_level())
+            .unwrap_or_default()
+    }
+    /// Set the least severe level logged by the scope at `index`
+    fn set_min_level(&self, index: usize, level: LogLevel) {
+        if let Some(scope) = self.scope(index) {
+            scope.set_min_level(level);
+        }
+    }
+    /// Whether the scope at `index` keeps logs at `level`; see
+    /// [crate::timer::Timer::log_enabled]
+    fn log_enabled(&self, index: usize, level: LogLevel) -> bool {
+        self.scope(index)
+            .is_some_and(|scope| scope.log_enabled(level))
+    }
+    /// Log to the scope at `index`; see [crate::timer::Timer::log_at]
+    fn log_at(
+        &self,
+        index: usize,
+        level: LogLevel,
+        log: Cow<'static, str>,
+        file: &'static str,
+        line: u32,
+    ) {
+        self.log_fields_at(index, level, log, Vec::new(), file, line);
+    }
+    /// Log with structured fields to the scope at `index`; see
+    /// [crate::timer::Timer::log_fields_at]
+    fn log_fields_at(
+        &self,
+        index: usize,
+        level: LogLevel,
+        log: Cow<'static, str>,
+        fields: Vec<(&'static str, String)>,
+        file: &'static str,
+        line: u32,
+    ) {
+        if let Some(scope) = self.scope(index) {
+            scope.log_fields_at(level, log, fields, file, line);
+        }
+    }
+    /// Current time of the clock of the scope at `index`, if there is one
+    fn now(&self, index: usize) -> Option<u64> {
+        self.scope(index).map(|scope| scope.now())
+    }
+    /// Record an iteration of the scope at `index` timed elsewhere; see
+    /// [crate::timer::Timer::record_iteration]
+    fn record_iteration(&self, index: usize, start: u64, end: u64, busy: u64) {
+        if let Some(scope) = self.scope(index) {
+            scope.record_iteration(start, end, busy);
+        }
+    }
+    /// Index of the scope called `name`. Defaults to its position in
+    /// [ProfilerExt::SCOPES], but may be decided at runtime.
+    fn scope_index(&self, name: &str) -> Option<usize> {
+        Self::SCOPES.iter().position(|scope| *scope == name)
+    }
+    /// Largest calibrated overhead of the clocks of the scopes; see
+    /// [crate::clock::Clock::overhead]
+    fn overhead(&self) -> Duration {
+        (0..)
+            .map_while(|index| self.scope(index))
+            .map(|scope| scope.overhead())
+            .max()
+            .unwrap_or_default()
+    }
+    /// Close any elapsed time-based windows of every scope; see
+    /// [crate::timer::Timer::close_elapsed_windows].
+    fn close_elapsed_windows(&self) {
+        (0..)
+            .map_while(|index| self.scope(index))
+            .for_each(|scope| scope.close_elapsed_windows());
+    }
+    /// Close the current window of every scope, even if not full; see
+    /// [crate::timer::Timer::flush].
+    fn flush(&self) {
+        (0..)
+            .map_while(|index| self.scope(index))
+            .for_each(|scope| scope.flush());
+    }
+}
+
+/// A single scope of a profiler, whatever its window size, history length and
+/// clock, for the methods of [ProfilerExt] that are about single scopes. Every
+/// [Timer] is one.
+pub trait ScopeExt {
+    /// Visit the summaries of the recent windows, oldest first; see
+    /// [crate::history::History::read]
+    fn read_windows(&self, f: &mut dyn FnMut(WindowStats));
+    /// See [Timer::update_lap_buffer]
+    fn update_lap_buffer(&self, buffer: &mut StateBuffer);
+    /// Read the logs of the scope that `logs` has not seen yet; see
+    /// [RecentLogs::update]
+    fn update_logs(&self, logs: &mut RecentLogs);
+    /// See [Timer::sampling]
+    fn sampling(&self) -> Sampling;
+    /// See [Timer::min_level]
+    fn min_level(&self) -> LogLevel;
+    /// See [Timer::set_min_level]
+    fn set_min_level(&self, level: LogLevel);
+    /// See [Timer::log_enabled]
+    fn log_enabled(&self, level: LogLevel) -> bool;
+    /// See [Timer::log_fields_at]
+    fn log_fields_at(
+        &self,
+        level: LogLevel,
+        log: Cow<'static, str>,
+        fields: Vec<(&'static str, String)>,
+        file: &'static str,
+        line: u32,
+    );
+    /// Current time of the scope's clock
+    fn now(&self) -> u64;
+    /// See [Timer::record_iteration]
+    fn record_iteration(&self, start: u64, end: u64, busy: u64);
+    /// Calibrated overhead of the scope's clock; see
+    /// [crate::clock::Clock::overhead]
+    fn overhead(&self) -> Duration;
+    /// See [Timer::close_elapsed_windows]
+    fn close_elapsed_windows(&self);
+    /// See [Timer::flush]
+    fn flush(&self);
+}
+
+#[cfg(not(feature = "disabled"))]
+impl<const W: usize, const A: usize, C: Clock> ScopeExt for Timer<W, A, C> {
+    fn read_windows(&self, f: &mut dyn FnMut(WindowStats)) {
+        self.recent_windows.read(f);
+    }
+
+    fn update_lap_buffer(&self, buffer: &mut StateBuffer) {
+        Timer::update_lap_buffer(self, buffer);
+    }
+
+    fn update_logs(&self, logs: &mut RecentLogs) {
+        logs.update(&self.logs);
+    }
+
+    fn sampling(&self) -> Sampling {
+        Timer::sampling(self)
+    }
+
+    fn min_level(&self) -> LogLevel {
+        Timer::min_level(self)
+    }
+
+    fn set_min_level(&self, level: LogLevel) {
+        Timer::set_min_level(self, level);
+    }
+
+    fn log_enabled(&self, level: LogLevel) -> bool {
+        Timer::log_enabled(self, level)
+    }
+
+    fn log_fields_at(
+        &self,
+        level: LogLevel,
+        log: Cow<'static, str>,
+        fields: Vec<(&'static str, String)>,
+        file: &'static str,
+        line: u32,
+    ) {
+        Timer::log_fields_at(self, level, log, fields, file, line);
+    }
+
+    fn now(&self) -> u64 {
+        self.clock.now()
+    }
+
+    fn record_iteration(&self, start: u64, end: u64, busy: u64) {
+        Timer::record_iteration(self, start, end, busy);
+    }
+
+    fn overhead(&self) -> Duration {
+        self.clock.overhead()
+    }
+
+    fn close_elapsed_windows(&self) {
+        Timer::close_elapsed_windows(self);
+    }
+
+    fn flush(&self) {
+        Timer::flush(self);
+    }
 }
@@ -0,0 +1,48 @@
+use std::sync::atomic::{AtomicUsize, Ordering};
+
+use crate::{
+    timer::Sample,
+    window::{Accumulator, Recorded, Windows},
+};
+
+/// Per-thread accumulator of a sharded [crate::timer::Timer] (see
+/// [crate::timer::Timer::enable_sharding]).
+///
+/// Each shard lives on its own cache lines, so threads recording into
+/// different shards never contend. Threads are assigned to shards round-robin
+/// the first time they record into any sharded timer; with more threads than
+/// shards some threads share a shard, which is still correct, just slower.
+#[repr(align(128))]
+#[derive(Default)]
+pub struct Shard {
+    /// Shards are windowed just like timers (without a limit), so that a merge
+    /// never loses or splits an iteration being recorded concurrently.
+    windows: Windows,
+}
+
+impl Shard {
+    /// Add a sample standing in for `iterations` iterations to the shard
+    pub(crate) fn add(&self, sample: &Sample, iterations: usize) -> Recorded {
+        self.windows.record(iterations, usize::MAX, |accumulator| {
+            accumulator.add(sample, iterations)
+        })
+    }
+
+    /// Hand everything accumulated so far to `merge`, if anything.
+    pub(crate) fn merge(&self, merge: impl FnOnce(&Accumulator)) {
+        let id = self.windows.active();
+        if self.windows.active_claimed() == 0 {
+            return;
+        }
+        self.windows.close(id, merge);
+    }
+}
+
+/// Index of the calling thread's shard, modulo the number of shards
+pub(crate) fn thread_shard(shards: usize) -> usize {
+    static NEXT_THREAD: AtomicUsize = AtomicUsize::new(0);
+    thread_local! {
+        static THREAD: usize = NEXT_THREAD.fetch_add(1, Ordering::Relaxed);
+    }
+    THREAD.with(|thread| *thread % shards)
+}
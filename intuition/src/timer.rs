@@ -2,36 +2,397 @@ use std::{
     borrow::Cow,
     fmt::Display,
     sync::{
-        atomic::{AtomicUsize, Ordering},
-        Mutex, MutexGuard,
+        atomic::{AtomicBool, Ordering},
+        Arc,
     },
-    time::Instant,
+    thread::ThreadId,
+    time::SystemTime,
 };
+#[cfg(not(feature = "disabled"))]
+use std::{
+    cell::Cell,
+    collections::hash_map::RandomState,
+    future::Future,
+    hash::{BuildHasher, Hasher},
+    panic::Location,
+    sync::{
+        atomic::{AtomicU64, AtomicU8, AtomicUsize},
+        OnceLock,
+    },
+    thread,
+    time::Duration,
+};
+
+#[cfg(feature = "disabled")]
+pub use crate::passthrough::{Instrumented, IterationGuard, Laps, Timer};
+#[cfg(not(feature = "disabled"))]
+use crate::{
+    calibration,
+    clock::{Clock, StdClock},
+    history::History,
+    lap::LapNames,
+    logs::{LogRing, RateLimit},
+    nesting::{self, Frame},
+    profiler::StateBuffer,
+    shard::{self, Shard},
+    window::{self, Accumulator, Windows},
+};
+#[cfg(not(feature = "disabled"))]
+pub use crate::{guard::IterationGuard, instrument::Instrumented, lap::Laps};
+use crate::{histogram::Percentiles, lap::MAX_LAPS};
+
+/// Global switch for timing; see [set_enabled].
+static ENABLED: AtomicBool = AtomicBool::new(true);
+
+/// Turn timing on or off for every [Timer] at once. While off, iterations
+/// still run but are not timed nor recorded. On by default.
+///
+/// With the `disabled` feature, timing is compiled out and this does nothing.
+pub fn set_enabled(enabled: bool) {
+    ENABLED.store(enabled, Ordering::Relaxed);
+}
+
+/// Whether timing is globally enabled; see [set_enabled].
+#[inline]
+pub fn is_enabled() -> bool {
+    !cfg!(feature = "disabled") && ENABLED.load(Ordering::Relaxed)
+}
 
 /// [Timer] is a submodule of a profiler; a profiler can contain many timers.
-pub struct Timer<const W: usize, const A: usize> {
+///
+/// Times are read from a [Clock], by default the standard library's
+/// monotonic clock ([StdClock]). With the `disabled` feature, this is a
+/// zero-sized passthrough instead (see [crate::passthrough]).
+#[cfg(not(feature = "disabled"))]
+pub struct Timer<const W: usize, const A: usize, C = StdClock> {
     pub total_count: AtomicUsize,
-    pub total_time: AtomicUsize,
-    pub recent_averages: Mutex<Vec<usize>>,
-    pub current_count: AtomicUsize,
-    pub current_time: AtomicUsize,
-    pub logs: Mutex<Vec<Log>>,
+    /// Sum of the times of the iterations that were timed (see
+    /// [Timer::set_sampling]), saturating at `u64::MAX`
+    pub total_time: AtomicU64,
+    /// Summary statistics of the most recent `A` windows
+    pub recent_windows: History<A>,
+    /// The window currently being filled
+    pub current: Windows,
+    /// Length of a time-based window in nanoseconds, or 0 to close windows
+    /// every `W` iterations instead
+    pub window_interval: AtomicU64,
+    /// Start of the current time-based window, in nanoseconds of `clock`
+    pub window_start: AtomicU64,
+    /// End of the most recently closed window, in nanoseconds of `clock`
+    pub window_closed_at: AtomicU64,
+    /// Held by the thread closing elapsed time-based windows, so that they
+    /// are closed one interval at a time
+    pub closing: AtomicBool,
+    /// Close count-based windows that have been open for this many
+    /// nanoseconds even if they are not full, or 0 to never do so; see
+    /// [Timer::set_flush_after]
+    pub flush_after: AtomicU64,
+    /// Source of all times and timestamps of this timer
+    pub clock: C,
+    /// Per-thread accumulators, if sharding is enabled
+    pub shards: OnceLock<Box<[Shard]>>,
+    /// Per-scope switch for timing; see [Timer::set_enabled]
+    pub enabled: AtomicBool,
+    /// Time 1 in this many iterations; see [Timer::set_sampling]
+    pub sample_rate: AtomicUsize,
+    /// Whether to pick the iterations to time at random rather than every
+    /// `sample_rate`th one
+    pub sample_randomly: AtomicBool,
+    /// Iterations not timed since the last one that was
+    pub unsampled: AtomicUsize,
+    /// Whether errors of [Timer::iteration_result] are logged
+    pub log_errors: AtomicBool,
+    /// Names of the laps recorded with [Timer::iteration_laps]
+    pub lap_names: LapNames,
+    /// Least severe [LogLevel] that is logged; see [Timer::set_min_level]
+    pub min_level: AtomicU8,
+    /// The most recent logs of this scope
+    pub logs: LogRing,
+}
+
+/// Summary statistics of one completed window, either of `W` iterations or of
+/// a fixed wall-clock interval (see [Timer::set_window_interval])
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq)]
+pub struct WindowStats {
+    /// Number of iterations completed in the window
+    pub count: usize,
+    /// Number of those iterations that were timed, which is `count` unless
+    /// sampling (see [Timer::set_sampling])
+    pub sampled: usize,
+    /// Sum of the iteration times of the window (of the timed iterations)
+    pub total: u64,
+    /// Part of `total` spent actually running rather than waiting (see
+    /// [Timer::instrument])
+    pub busy: u64,
+    /// Part of `total` not spent in scopes nested inside this one
+    pub self_time: u64,
+    /// Number of timed iterations that returned an `Err` (see
+    /// [Timer::iteration_result])
+    pub failures: usize,
+    /// Part of `total` spent in those failed iterations
+    pub failure_total: u64,
+    /// Part of `total` spent in each lap (see [Timer::iteration_laps]),
+    /// indexed like [Timer::lap_names]
+    pub laps: [u64; MAX_LAPS],
+    /// Number of timed iterations that ended each lap, indexed like `laps`
+    pub lap_counts: [usize; MAX_LAPS],
+    /// Wall-clock time between the end of the previous window and the end of
+    /// this one, in nanoseconds
+    pub elapsed: u64,
+    pub mean: u64,
+    pub percentiles: Percentiles,
+    /// Whether this count-based window was closed (see [Timer::flush]) before
+    /// it held `W` iterations
+    pub partial: bool,
+}
+
+impl WindowStats {
+    /// Completed iterations per second over the window
+    pub fn throughput(&self) -> u64 {
+        if self.elapsed == 0 {
+            return 0;
+        }
+        (self.count as u128 * 1_000_000_000 / self.elapsed as u128)
+            .try_into()
+            .unwrap_or(u64::MAX)
+    }
+
+    /// Average time spent in lap `index` per timed iteration that ended it
+    pub fn lap_mean(&self, index: usize) -> u64 {
+        self.laps[index]
+            .checked_div(self.lap_counts[index] as u64)
+            .unwrap_or(0)
+    }
+}
+
+/// A statistic of a window that can be plotted on the dashboard
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq)]
+pub enum Statistic {
+    #[default]
+    Mean,
+    Min,
+    P50,
+    P90,
+    P99,
+    Max,
+    /// Average time excluding nested scopes
+    SelfTime,
+    /// Average time spent running
+    Busy,
+    /// Average time spent waiting, i.e. the average minus [Statistic::Busy]
+    Waiting,
+    /// Average time of the iterations that returned `Ok` (see
+    /// [Timer::iteration_result])
+    OkMean,
+    /// Average time of the iterations that returned `Err`
+    ErrMean,
+    /// Failed iterations per thousand timed ones
+    ErrorRate,
+    Count,
+}
+
+impl Statistic {
+    pub const ALL: [Statistic; 13] = [
+        Statistic::Mean,
+        Statistic::Min,
+        Statistic::P50,
+        Statistic::P90,
+        Statistic::P99,
+        Statistic::Max,
+        Statistic::SelfTime,
+        Statistic::Busy,
+        Statistic::Waiting,
+        Statistic::OkMean,
+        Statistic::ErrMean,
+        Statistic::ErrorRate,
+        Statistic::Count,
+    ];
+
+    /// The statistic following this one, wrapping around
+    pub fn next(self) -> Statistic {
+        let index = Self::ALL.iter().position(|s| *s == self).unwrap();
+        Self::ALL[(index + 1) % Self::ALL.len()]
+    }
+
+    /// Whether this statistic is a time (in nanoseconds) rather than a count
+    /// or rate
+    pub fn is_time(self) -> bool {
+        !matches!(self, Statistic::Count | Statistic::ErrorRate)
+    }
+
+    /// Extract this statistic from a window
+    pub fn of(self, stats: &WindowStats) -> u64 {
+        let sampled = stats.sampled as u64;
+        let failures = stats.failures as u64;
+        match self {
+            Statistic::Mean => stats.mean,
+            Statistic::Min => stats.percentiles.min,
+            Statistic::P50 => stats.percentiles.p50,
+            Statistic::P90 => stats.percentiles.p90,
+            Statistic::P99 => stats.percentiles.p99,
+            Statistic::Max => stats.percentiles.max,
+            Statistic::SelfTime => stats.self_time.checked_div(sampled).unwrap_or(0),
+            Statistic::Busy => stats.busy.checked_div(sampled).unwrap_or(0),
+            Statistic::Waiting => (stats.total.saturating_sub(stats.busy))
+                .checked_div(sampled)
+                .unwrap_or(0),
+            Statistic::OkMean => (stats.total.saturating_sub(stats.failure_total))
+                .checked_div(sampled.saturating_sub(failures))
+                .unwrap_or(0),
+            Statistic::ErrMean => stats.failure_total.checked_div(failures).unwrap_or(0),
+            Statistic::ErrorRate => (failures.saturating_mul(1000))
+                .checked_div(sampled)
+                .unwrap_or(0),
+            Statistic::Count => stats.count as u64,
+        }
+    }
+}
+
+impl Display for Statistic {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            Statistic::Mean => f.write_str("Average"),
+            Statistic::Min => f.write_str("Min"),
+            Statistic::P50 => f.write_str("p50"),
+            Statistic::P90 => f.write_str("p90"),
+            Statistic::P99 => f.write_str("p99"),
+            Statistic::Max => f.write_str("Max"),
+            Statistic::SelfTime => f.write_str("Self"),
+            Statistic::Busy => f.write_str("Busy"),
+            Statistic::Waiting => f.write_str("Waiting"),
+            Statistic::OkMean => f.write_str("Ok average"),
+            Statistic::ErrMean => f.write_str("Err average"),
+            Statistic::ErrorRate => f.write_str("Errors (per mille)"),
+            Statistic::Count => f.write_str("Count"),
+        }
+    }
+}
+
+/// Which iterations of a [Timer] are timed; see [Timer::set_sampling]
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq)]
+pub enum Sampling {
+    /// Time every iteration
+    #[default]
+    All,
+    /// Time every Nth iteration
+    Every(usize),
+    /// Time each iteration with a probability of 1/N
+    Random(usize),
+}
+
+impl Display for Sampling {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            Sampling::All => f.write_str("all"),
+            Sampling::Every(n) => write!(f, "1/{n}"),
+            Sampling::Random(n) => write!(f, "~1/{n}"),
+        }
+    }
 }
 
+/// The times of a single iteration, or the totals of a window of them, in
+/// nanoseconds
+#[cfg(not(feature = "disabled"))]
+#[derive(Clone, Copy, Debug, Default)]
+pub(crate) struct Sample {
+    /// Wall-clock (inclusive) time
+    pub(crate) time: u64,
+    /// Time spent running rather than waiting
+    pub(crate) busy: u64,
+    /// Time not spent in nested scopes
+    pub(crate) self_time: u64,
+    /// Number of failed iterations; see [Timer::iteration_result]
+    pub(crate) failures: usize,
+    /// Part of `time` spent in failed iterations
+    pub(crate) failure_time: u64,
+    /// Part of `time` spent in each lap
+    pub(crate) laps: [u64; MAX_LAPS],
+    /// Number of iterations that ended each lap
+    pub(crate) lap_counts: [usize; MAX_LAPS],
+}
+
+/// A log record of a scope
+#[derive(Clone, Debug)]
 pub struct Log {
+    /// Number of logs of the scope before this one; see [LogRing]
+    pub seq: u64,
     pub level: LogLevel,
     pub log: Cow<'static, str>,
+    /// Structured `key = value` fields logged with the message, e.g. by
+    /// [crate::warn!]
+    pub fields: Vec<(&'static str, String)>,
+    /// Wall-clock time at which it was logged
+    pub time: SystemTime,
+    /// Timestamp of the scope's [Clock] at which it was logged, comparable
+    /// with the times of its windows
+    pub monotonic: u64,
+    /// Name of the thread that logged it, if the thread has one
+    pub thread_name: Option<Arc<str>>,
+    pub thread_id: ThreadId,
+    /// Source file and line it was logged from
+    pub file: &'static str,
+    pub line: u32,
+    /// Number of consecutive times it was logged; see [LogRing]
+    pub count: u64,
+    /// Wall-clock time at which it was last logged
+    pub last_time: SystemTime,
+    /// Timestamp of the scope's [Clock] at which it was last logged
+    pub last_monotonic: u64,
 }
 
+impl Log {
+    /// Whether `other` is the same log again, i.e. a repeat of this one
+    pub fn repeated_by(&self, other: &Log) -> bool {
+        self.level == other.level
+            && self.line == other.line
+            && self.file == other.file
+            && self.log == other.log
+            && self.fields == other.fields
+    }
+}
+
+/// Severity of a [Log], from least to most severe
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq, PartialOrd, Ord, Hash)]
+#[repr(u8)]
 pub enum LogLevel {
+    Trace,
+    Debug,
+    #[default]
     Info,
     Warn,
     Error,
 }
 
+impl LogLevel {
+    pub const ALL: [LogLevel; 5] = [
+        LogLevel::Trace,
+        LogLevel::Debug,
+        LogLevel::Info,
+        LogLevel::Warn,
+        LogLevel::Error,
+    ];
+
+    /// The next more severe level, if any
+    pub fn raise(self) -> LogLevel {
+        Self::ALL[(self as usize + 1).min(Self::ALL.len() - 1)]
+    }
+
+    /// The next less severe level, if any
+    pub fn lower(self) -> LogLevel {
+        Self::ALL[(self as usize).saturating_sub(1)]
+    }
+
+    #[cfg(not(feature = "disabled"))]
+    fn from_u8(level: u8) -> LogLevel {
+        Self::ALL[(level as usize).min(Self::ALL.len() - 1)]
+    }
+}
+
 impl Display for LogLevel {
     fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
         match self {
+            LogLevel::Trace => f.write_str("TRACE"),
+            LogLevel::Debug => f.write_str("DEBUG"),
             LogLevel::Info => f.write_str("INFO"),
             LogLevel::Warn => f.write_str("WARN"),
             LogLevel::Error => f.write_str("ERROR"),
@@ -39,96 +400,762 @@ impl Display for LogLevel {
     }
 }
 
-impl<const W: usize, const A: usize> Timer<W, A> {
+#[cfg(not(feature = "disabled"))]
+impl<const W: usize, const A: usize, C: Clock> Timer<W, A, C> {
     /// Record an iteration time in nanoseconds
     pub fn iteration<T, F: FnOnce() -> T>(&self, iter: F) -> T {
-        let start = Instant::now();
+        let Some(iterations) = self.sample() else {
+            return iter();
+        };
+        let frame = nesting::enter();
+        let start = self.clock.now();
         let output = iter();
+        self.record(frame, start, self.clock.now(), iterations, false);
+        output
+    }
+
+    /// Like [Timer::iteration], but for fallible iterations. Iterations that
+    /// return `Ok` and `Err` are also counted and timed separately, for the
+    /// error rate ([Statistic::ErrorRate]) and separate latencies
+    /// ([Statistic::OkMean], [Statistic::ErrMean]) of each window.
+    ///
+    /// If enabled with [Timer::set_log_errors], errors are also logged.
+    ///
+    /// ```rust
+    /// # use intuition::timer::Timer;
+    /// # let timer: Timer<10, 10> = Timer::default();
+    /// timer.set_log_errors(true);
+    /// let parsed = timer.iteration_result(|| "seven".parse::<u32>());
+    /// assert!(parsed.is_err());
+    /// ```
+    #[track_caller]
+    pub fn iteration_result<T, E, F>(&self, iter: F) -> Result<T, E>
+    where
+        E: Display,
+        F: FnOnce() -> Result<T, E>,
+    {
+        let result = match self.sample() {
+            None => iter(),
+            Some(iterations) => {
+                let frame = nesting::enter();
+                let start = self.clock.now();
+                let result = iter();
+                self.record(frame, start, self.clock.now(), iterations, result.is_err());
+                result
+            }
+        };
+        if let Err(error) = &result {
+            if self.log_errors.load(Ordering::Relaxed) && self.log_enabled(LogLevel::Error) {
+                let caller = Location::caller();
+                self.log_at(
+                    LogLevel::Error,
+                    error.to_string(),
+                    caller.file(),
+                    caller.line(),
+                );
+            }
+        }
+        result
+    }
+
+    /// Like [Timer::iteration], but also times the phases of the iteration.
+    /// Each phase ends with a call to [Laps::lap] naming it; the time after
+    /// the last lap is not attributed to any. Lap times are summed per window
+    /// ([WindowStats::laps]) and shown as a stacked chart on the dashboard.
+    ///
+    /// ```rust
+    /// # use intuition::timer::Timer;
+    /// # let timer: Timer<10, 10> = Timer::default();
+    /// let sum = timer.iteration_laps(|laps| {
+    ///     let numbers: Vec<u64> = (0..100).collect();
+    ///     laps.lap("generate");
+    ///     let sum: u64 = numbers.iter().sum();
+    ///     laps.lap("sum");
+    ///     sum
+    /// });
+    /// assert_eq!(sum, 4950);
+    /// ```
+    pub fn iteration_laps<T, F: FnOnce(&mut Laps<'_, C>) -> T>(&self, iter: F) -> T {
+        let Some(iterations) = self.sample() else {
+            return iter(&mut Laps::new(&self.clock, &self.lap_names, None));
+        };
+        let frame = nesting::enter();
+        let start = self.clock.now();
+        let mut laps = Laps::new(&self.clock, &self.lap_names, Some(start));
+        let output = iter(&mut laps);
+        self.record_with_laps(frame, start, self.clock.now(), iterations, &laps);
+        output
+    }
+
+    /// Fill `buffer` with the name of every lap of this scope (see
+    /// [Timer::iteration_laps]) and its average time in each recent window
+    pub fn update_lap_buffer(&self, buffer: &mut StateBuffer) {
+        // Reuse the values of the laps already in the buffer, only allocating
+        // for laps seen since the last update
+        let mut laps = 0;
+        for name in self.lap_names.names() {
+            match buffer.get_mut(laps) {
+                Some((lap, values)) => {
+                    *lap = name;
+                    values.clear();
+                }
+                None => buffer.push((name, Vec::with_capacity(A))),
+            }
+            laps += 1;
+        }
+        buffer.truncate(laps);
+        self.recent_windows.read(|window| {
+            for (index, (_, values)) in buffer.iter_mut().enumerate() {
+                values.push(window.lap_mean(index));
+            }
+        });
+    }
+
+    /// Log the `Display` output of every `Err` returned to
+    /// [Timer::iteration_result] at [LogLevel::Error]. Off by default.
+    pub fn set_log_errors(&self, log_errors: bool) {
+        self.log_errors.store(log_errors, Ordering::Relaxed);
+    }
+
+    /// Start timing an iteration that ends when the returned guard is dropped
+    /// (or [IterationGuard::finish]ed), unless it is [IterationGuard::cancel]led.
+    ///
+    /// Unlike [Timer::iteration], this plays well with `?`, early `return`s and
+    /// `break`/`continue` in the enclosing code.
+    ///
+    /// ```rust
+    /// # use intuition::timer::Timer;
+    /// # let timer: Timer<10, 10> = Timer::default();
+    /// fn parse(timer: &Timer<10, 10>, s: &str) -> Result<u32, std::num::ParseIntError> {
+    ///     let _guard = timer.start();
+    ///     let n: u32 = s.parse()?;
+    ///     Ok(n * 2)
+    /// }
+    /// # parse(&timer, "21").unwrap();
+    /// ```
+    pub fn start(&self) -> IterationGuard<'_, W, A, C> {
+        IterationGuard::new(self)
+    }
+
+    /// Time a future from its first poll until it completes.
+    ///
+    /// Besides this wall-clock time, the time spent inside `poll` is recorded
+    /// separately as busy time, so the dashboard can tell time spent working
+    /// ([Statistic::Busy]) from time spent waiting ([Statistic::Waiting]).
+    /// This works with any executor.
+    pub fn instrument<F: Future>(&self, future: F) -> Instrumented<'_, F, W, A, C> {
+        Instrumented::new(self, future)
+    }
+
+    /// Turn timing of this scope on or off. While off, iterations still run
+    /// but are not timed nor recorded; time spent in them counts towards the
+    /// self time of the enclosing scope. On by default.
+    ///
+    /// Timing also has to be enabled globally (see [set_enabled]).
+    pub fn set_enabled(&self, enabled: bool) {
+        self.enabled.store(enabled, Ordering::Relaxed);
+    }
+
+    /// Whether iterations of this scope are currently timed. Always `false`
+    /// with the `disabled` feature.
+    #[inline]
+    pub fn is_enabled(&self) -> bool {
+        is_enabled() && self.enabled.load(Ordering::Relaxed)
+    }
+
+    /// Number of iterations of this scope completed so far, timed or not,
+    /// including the ones not yet stood in for by a timed iteration when
+    /// sampling (see [Timer::set_sampling]). Always 0 with the `disabled`
+    /// feature.
+    pub fn total_count(&self) -> usize {
+        self.total_count
+            .load(Ordering::Acquire)
+            .saturating_add(self.unsampled.load(Ordering::Relaxed))
+    }
+
+    /// Only time some iterations of this scope, e.g. for scopes so short that
+    /// reading the clock costs about as much as the iteration itself. The
+    /// other iterations only bump a counter.
+    ///
+    /// Each timed iteration stands in for the iterations that were not timed
+    /// since the previous one, so window means and percentiles are estimated
+    /// from the timed iterations while counts and throughput still cover all
+    /// of them. Futures (see [Timer::instrument]) are always timed.
+    ///
+    /// Untimed iterations do not take part in nesting: their time counts
+    /// towards the self time of the enclosing scope.
+    pub fn set_sampling(&self, sampling: Sampling) {
+        let (rate, randomly) = match sampling {
+            Sampling::All => (1, false),
+            Sampling::Every(n) => (n, false),
+            Sampling::Random(n) => (n, true),
+        };
+        self.sample_randomly.store(randomly, Ordering::Relaxed);
+        self.sample_rate.store(rate.max(1), Ordering::Relaxed);
+    }
+
+    /// Which iterations of this scope are timed; see [Timer::set_sampling]
+    pub fn sampling(&self) -> Sampling {
+        match self.sample_rate.load(Ordering::Relaxed) {
+            0 | 1 => Sampling::All,
+            n if self.sample_randomly.load(Ordering::Relaxed) => Sampling::Random(n),
+            n => Sampling::Every(n),
+        }
+    }
+
+    /// Decide whether to time the iteration that is about to start. Returns
+    /// the number of iterations it stands for if so, or `None` if it should
+    /// only be counted (or not even that, if timing is disabled).
+    #[inline]
+    pub(crate) fn sample(&self) -> Option<usize> {
+        if !self.is_enabled() {
+            return None;
+        }
+        let rate = self.sample_rate.load(Ordering::Relaxed);
+        if rate <= 1 {
+            return Some(1);
+        }
+
+        let unsampled = self.unsampled.fetch_add(1, Ordering::Relaxed) + 1;
+        let sampled = if self.sample_randomly.load(Ordering::Relaxed) {
+            random().is_multiple_of(rate as u64)
+        } else {
+            unsampled >= rate
+        };
+        if !sampled {
+            return None;
+        }
+        // If another thread took the counter (including this iteration) first,
+        // that one stands in for this iteration instead
+        match self.unsampled.swap(0, Ordering::Relaxed) {
+            0 => None,
+            iterations => Some(iterations),
+        }
+    }
+
+    /// Record a synchronous iteration which ran (without waiting) from `start`
+    /// to `end` (timestamps of `clock`) in `frame`, standing in for
+    /// `iterations` iterations, which `failed` or not.
+    pub(crate) fn record(
+        &self,
+        frame: Frame,
+        start: u64,
+        end: u64,
+        iterations: usize,
+        failed: bool,
+    ) {
+        let time = calibration::correct(nanos_between(start, end), &self.clock);
+        let children = frame.exit(time);
         self.add_time(
-            start
-                .elapsed()
-                .as_nanos()
-                .try_into()
-                .expect("nanos shouldn't overflow usize"),
+            Sample {
+                time,
+                busy: time,
+                self_time: time.saturating_sub(children),
+                failures: failed as usize,
+                failure_time: if failed { time } else { 0 },
+                ..Default::default()
+            },
+            iterations,
+            end,
         );
-        output
     }
 
-    /// This function takes the loop time and adds it to total and current time,
-    /// increments total and current count, and resets current/recent time and
-    /// count if necessary.
-    fn add_time(&self, loop_time: usize) {
-        // Add to times
-        self.total_time.fetch_add(loop_time, Ordering::AcqRel);
-        self.current_time.fetch_add(loop_time, Ordering::AcqRel);
+    /// Like [Timer::record], for an iteration whose phases were timed by `laps`
+    fn record_with_laps(
+        &self,
+        frame: Frame,
+        start: u64,
+        end: u64,
+        iterations: usize,
+        laps: &Laps<'_, C>,
+    ) {
+        let time = calibration::correct(nanos_between(start, end), &self.clock);
+        let children = frame.exit(time);
+        self.add_time(
+            Sample {
+                time,
+                busy: time,
+                self_time: time.saturating_sub(children),
+                laps: laps.times(),
+                lap_counts: laps.counts(),
+                ..Default::default()
+            },
+            iterations,
+            end,
+        );
+    }
+
+    /// Record an iteration timed elsewhere, e.g. a `tracing` span, which ran
+    /// from `start` to `end` (timestamps of [Timer::clock]), of which `busy`
+    /// nanoseconds were spent running. Like futures, such iterations do not
+    /// take part in nesting.
+    ///
+    /// Whether it is recorded is decided like for any other iteration (see
+    /// [Timer::set_enabled] and [Timer::set_sampling]).
+    pub fn record_iteration(&self, start: u64, end: u64, busy: u64) {
+        if let Some(iterations) = self.sample() {
+            self.record_with_busy(start, end, busy, iterations);
+        }
+    }
+
+    /// Record an iteration which ran from `start` to `end`, of which only
+    /// `busy` nanoseconds were spent running, standing in for `iterations`
+    /// iterations. Such iterations do not take part in nesting.
+    pub(crate) fn record_with_busy(&self, start: u64, end: u64, busy: u64, iterations: usize) {
+        let time = nanos_between(start, end);
+        self.add_time(
+            Sample {
+                time,
+                busy,
+                self_time: time,
+                ..Default::default()
+            },
+            iterations,
+            end,
+        );
+    }
+
+    /// Close windows every `interval` of wall-clock time instead of every `W`
+    /// iterations. Intervals in which the scope did not complete a single
+    /// iteration are recorded as empty windows. Passing `None` switches back
+    /// to count-based windows.
+    ///
+    /// The window open until then is closed right away with what it holds
+    /// (like [Timer::flush]), so that the first window of the new mode only
+    /// covers its own iterations and time.
+    pub fn set_window_interval(&self, interval: Option<Duration>) {
+        let interval: u64 = interval
+            .map(|i| i.as_nanos().try_into().unwrap_or(u64::MAX))
+            .unwrap_or(0);
+        let now = self.clock.now();
+        self.flush_at(now);
+        self.window_closed_at.store(now, Ordering::Release);
+        self.window_start.store(now, Ordering::Release);
+        self.window_interval.store(interval, Ordering::Release);
+    }
+
+    /// Close every time-based window whose interval has fully elapsed. This is
+    /// done on every iteration anyway, but should also be called periodically
+    /// (e.g. by the dashboard) so that a scope that stops running still emits
+    /// (empty) windows.
+    ///
+    /// For count-based windows this closes the current window if it has been
+    /// open for longer than set with [Timer::set_flush_after]. For a sharded
+    /// timer this also merges the per-thread shards, which is when count-based
+    /// windows of a sharded timer close.
+    pub fn close_elapsed_windows(&self) {
+        let now = self.clock.now();
+        if self.window_interval.load(Ordering::Acquire) == 0 {
+            self.merge_shards(now);
+            let flush_after = self.flush_after.load(Ordering::Relaxed);
+            let opened_at = self.window_closed_at.load(Ordering::Acquire);
+            if flush_after != 0 && now.saturating_sub(opened_at) >= flush_after {
+                self.flush_at(now);
+            }
+        } else {
+            self.close_elapsed_windows_at(now);
+        }
+    }
+
+    /// Close the current count-based window now, even if it holds fewer than
+    /// `W` iterations, unless it is empty. The window is recorded with the
+    /// iterations it does hold and marked as [WindowStats::partial], so that
+    /// idle or slow scopes still show up to date data.
+    ///
+    /// Does nothing for time-based windows, which close on time anyway.
+    pub fn flush(&self) {
+        if self.window_interval.load(Ordering::Acquire) == 0 {
+            self.flush_at(self.clock.now());
+        }
+    }
+
+    /// [Timer::flush] count-based windows which have been open for at least
+    /// `after`, checked whenever [Timer::close_elapsed_windows] is called (e.g.
+    /// on every tick of the dashboard). `None` (the default) only closes
+    /// windows once they are full.
+    pub fn set_flush_after(&self, after: Option<Duration>) {
+        let after = after.map_or(0, |after| {
+            after.as_nanos().max(1).try_into().unwrap_or(u64::MAX)
+        });
+        self.flush_after.store(after, Ordering::Relaxed);
+    }
+
+    fn flush_at(&self, now: u64) {
+        self.merge_shards(now);
+        let id = self.current.active();
+        if self.current.active_claimed() > 0 {
+            self.close_window(id, now, 0);
+        }
+    }
+
+    /// Accumulate iterations into `shards` per-thread slots instead of into
+    /// the shared counters, so that threads sharing this timer don't contend
+    /// on the same cache line on every iteration. Should be enabled before the
+    /// timer is used, and cannot be disabled again.
+    ///
+    /// The shards are merged into the shared counters (and windows closed)
+    /// whenever a shard accumulates `W` iterations, a time-based window ends,
+    /// or [Timer::close_elapsed_windows] is called (e.g. by the dashboard).
+    /// As a consequence `total_count` and `total_time` lag behind by up to
+    /// `W` iterations per thread, and count-based windows may hold somewhat
+    /// more than `W` iterations.
+    pub fn enable_sharding(&self, shards: usize) {
+        let _ = self
+            .shards
+            .set((0..shards.max(1)).map(|_| Shard::default()).collect());
+    }
+
+    /// Merge every shard into the shared counters, closing a count-based
+    /// window if that filled it.
+    fn merge_shards(&self, now: u64) {
+        if let Some(shards) = self.shards.get() {
+            for shard in shards.iter() {
+                shard.merge(|accumulator| self.merge_shard(accumulator, now));
+            }
+        }
+    }
+
+    fn merge_shard(&self, accumulator: &Accumulator, now: u64) {
+        let recorded = self
+            .current
+            .record(accumulator.count(), self.window_limit(), |current| {
+                let (count, totals) = accumulator.drain_into(current);
+                window::add_saturating(&self.total_time, totals.time);
+                self.total_count.fetch_add(count, Ordering::Relaxed);
+            });
+        if recorded.filled {
+            self.close_window(recorded.id, now, 0);
+        }
+    }
+
+    /// Maximum number of iterations in a window
+    fn window_limit(&self) -> usize {
+        if self.window_interval.load(Ordering::Acquire) == 0 {
+            W
+        } else {
+            usize::MAX
+        }
+    }
+
+    fn close_elapsed_windows_at(&self, now: u64) {
+        let interval = self.window_interval.load(Ordering::Acquire);
+        if interval == 0 {
+            return;
+        }
+        if now
+            < self
+                .window_start
+                .load(Ordering::Acquire)
+                .saturating_add(interval)
+        {
+            return;
+        }
+        // Another thread is closing the window; iterations ending meanwhile
+        // go into the next one
+        if self.closing.swap(true, Ordering::Acquire) {
+            return;
+        }
+
+        // Move the start forward by a whole number of intervals and close the
+        // window that was active until then. Both happen under the lock, so
+        // that a window is always closed with the end of its own interval.
+        let start = self.window_start.load(Ordering::Acquire);
+        if now >= start.saturating_add(interval) {
+            let elapsed_windows = (now - start) / interval;
+            self.window_start
+                .store(start + elapsed_windows * interval, Ordering::Release);
+            self.merge_shards(now);
+            self.close_window(
+                self.current.active(),
+                start.saturating_add(interval),
+                // Intervals in which nothing completed at all. Anything beyond
+                // A of them would be overwritten right away, so don't bother.
+                (elapsed_windows - 1).min(A as u64),
+            );
+        }
+        self.closing.store(false, Ordering::Release);
+    }
+
+    /// This function takes the loop time of a sample standing in for
+    /// `iterations` iterations and adds it to the total and current window,
+    /// closing the window if necessary.
+    fn add_time(&self, sample: Sample, iterations: usize, now: u64) {
+        // In time-based mode, first close the window if this iteration
+        // finished after its end
+        let count_based = self.window_interval.load(Ordering::Acquire) == 0;
+        if !count_based {
+            self.close_elapsed_windows_at(now);
+        }
+
+        // Sharded timers only touch this thread's shard
+        if let Some(shards) = self.shards.get() {
+            let shard = &shards[shard::thread_shard(shards.len())];
+            if shard.add(&sample, iterations).claimed >= W && count_based {
+                shard.merge(|accumulator| self.merge_shard(accumulator, now));
+            }
+            return;
+        }
 
-        // Increment counters
-        self.total_count.fetch_add(1, Ordering::AcqRel);
-        // fetch_add returns previous value so we add 1 to get the value we stored
-        let current_count = self.current_count.fetch_add(1, Ordering::AcqRel) + 1;
+        // Add to totals and current window
+        window::add_saturating(&self.total_time, sample.time);
+        self.total_count.fetch_add(iterations, Ordering::Relaxed);
+        let recorded = self
+            .current
+            .record(iterations, self.window_limit(), |current| {
+                current.add(&sample, iterations)
+            });
 
         // Check if time to average
-        if current_count == W {
-            // Reset current_count
-            self.current_count.store(0, Ordering::Release);
+        if recorded.filled {
+            self.close_window(recorded.id, now, 0);
+        }
+    }
 
-            // Reset timer but get last value via fetch_update
-            let current_time: usize = self
-                .current_time
-                .fetch_update(Ordering::AcqRel, Ordering::Acquire, |_| Some(0))
-                .unwrap();
+    /// Close window `id` (unless someone else already did), which ended at
+    /// `closed_at`, and add its summary to the history, followed by
+    /// `empty_windows` empty time-based windows.
+    fn close_window(&self, id: usize, closed_at: u64, empty_windows: u64) {
+        self.current.close(id, |current| {
+            let summary = current.take();
+            let opened_at = self.window_closed_at.swap(closed_at, Ordering::AcqRel);
 
             // Calculate recent average
-            let recent_average: usize = current_time / W;
-
-            #[allow(unused_labels)]
-            'mutex_scope: {
-                let mut recent_averages: MutexGuard<Vec<usize>> =
-                    self.recent_averages.lock().unwrap();
-                // If at capacity, remove front element before adding to back
-                if recent_averages.len() == A {
-                    recent_averages.remove(0);
+            let recent_average: u64 = summary
+                .totals
+                .time
+                .checked_div(summary.sampled as u64)
+                .unwrap_or(0);
+            let recent_stats = WindowStats {
+                count: summary.count,
+                sampled: summary.sampled,
+                total: summary.totals.time,
+                busy: summary.totals.busy,
+                self_time: summary.totals.self_time,
+                failures: summary.totals.failures,
+                failure_total: summary.totals.failure_time,
+                laps: summary.totals.laps,
+                lap_counts: summary.totals.lap_counts,
+                elapsed: closed_at.saturating_sub(opened_at),
+                mean: recent_average,
+                percentiles: summary.percentiles,
+                partial: self.window_interval.load(Ordering::Acquire) == 0 && summary.count < W,
+            };
+
+            // Add to back of history, overwriting the oldest window if at
+            // capacity. NOTE: this never blocks, even while the dashboard is
+            // reading the history. Windows are closed one at a time, so they
+            // are also pushed in order.
+            self.recent_windows.push(recent_stats);
+
+            if empty_windows > 0 {
+                let interval = self.window_interval.load(Ordering::Acquire);
+                for _ in 0..empty_windows {
+                    self.recent_windows.push(WindowStats {
+                        elapsed: interval,
+                        ..Default::default()
+                    });
                 }
-                // Add to back
-                // NOTE: because we hold lock, should never double remove or have
-                // anything weird happen in between remove and push
-                recent_averages.push(recent_average);
+                self.window_closed_at.store(
+                    closed_at.saturating_add(empty_windows.saturating_mul(interval)),
+                    Ordering::Release,
+                );
             }
-        }
+        });
+    }
+
+    /// Only keep logs of this scope at `level` or more severe; the others are
+    /// dropped right away. [LogLevel::Info] by default.
+    pub fn set_min_level(&self, level: LogLevel) {
+        self.min_level.store(level as u8, Ordering::Relaxed);
+    }
+
+    /// Limit the rate of new logs of this scope; see [LogRing::set_rate_limit]
+    pub fn set_log_rate_limit(&self, limit: Option<RateLimit>) {
+        self.logs.set_rate_limit(limit);
+    }
+
+    /// Least severe level logged; see [Timer::set_min_level]
+    pub fn min_level(&self) -> LogLevel {
+        LogLevel::from_u8(self.min_level.load(Ordering::Relaxed))
+    }
+
+    /// Whether logs at `level` are kept. This is cheap, so check it before
+    /// formatting an expensive log (which the logging macros such as
+    /// [crate::debug!] do for you). Always `false` with the `disabled`
+    /// feature.
+    ///
+    /// ```rust
+    /// # use intuition::timer::{LogLevel, Timer};
+    /// # let timer: Timer<10, 10> = Timer::default();
+    /// # let queue = [1, 2, 3];
+    /// if timer.log_enabled(LogLevel::Debug) {
+    ///     timer.debug(format!("queue: {queue:?}"));
+    /// }
+    /// ```
+    #[inline]
+    pub fn log_enabled(&self, level: LogLevel) -> bool {
+        !cfg!(feature = "disabled") && level as u8 >= self.min_level.load(Ordering::Relaxed)
+    }
+
+    /// Log at [LogLevel::Trace], recording where it was called from
+    #[track_caller]
+    pub fn trace<L: Into<Cow<'static, str>>>(&self, log: L) {
+        let caller = Location::caller();
+        self.log_at(LogLevel::Trace, log, caller.file(), caller.line());
     }
 
+    /// Log at [LogLevel::Debug], recording where it was called from
+    #[track_caller]
+    pub fn debug<L: Into<Cow<'static, str>>>(&self, log: L) {
+        let caller = Location::caller();
+        self.log_at(LogLevel::Debug, log, caller.file(), caller.line());
+    }
+
+    /// Log at [LogLevel::Info], recording where it was called from
+    #[track_caller]
     pub fn info<L: Into<Cow<'static, str>>>(&self, log: L) {
-        self.logs.lock().unwrap().push(Log {
-            level: LogLevel::Info,
-            log: log.into(),
-        });
+        let caller = Location::caller();
+        self.log_at(LogLevel::Info, log, caller.file(), caller.line());
     }
 
+    /// Log at [LogLevel::Error], recording where it was called from
+    #[track_caller]
     pub fn error<L: Into<Cow<'static, str>>>(&self, log: L) {
-        self.logs.lock().unwrap().push(Log {
-            level: LogLevel::Error,
-            log: log.into(),
-        });
+        let caller = Location::caller();
+        self.log_at(LogLevel::Error, log, caller.file(), caller.line());
     }
 
+    /// Log at [LogLevel::Warn], recording where it was called from
+    #[track_caller]
     pub fn warn<L: Into<Cow<'static, str>>>(&self, log: L) {
-        self.logs.lock().unwrap().push(Log {
-            level: LogLevel::Warn,
+        let caller = Location::caller();
+        self.log_at(LogLevel::Warn, log, caller.file(), caller.line());
+    }
+
+    /// Log as if called from `line` of `file`, e.g. from a logging macro (with
+    /// `file!()` and `line!()`) or when forwarding records of another logger.
+    /// The time and the calling thread are recorded as well.
+    ///
+    /// Logs below the minimum level ([Timer::set_min_level]) are dropped
+    /// before anything is recorded, and new logs beyond the rate limit
+    /// ([Timer::set_log_rate_limit]) when pushed.
+    pub fn log_at<L: Into<Cow<'static, str>>>(
+        &self,
+        level: LogLevel,
+        log: L,
+        file: &'static str,
+        line: u32,
+    ) {
+        self.log_fields_at(level, log, Vec::new(), file, line);
+    }
+
+    /// Like [Timer::log_at], with structured `key = value` fields stored
+    /// alongside the message ([Log::fields])
+    pub fn log_fields_at<L: Into<Cow<'static, str>>>(
+        &self,
+        level: LogLevel,
+        log: L,
+        fields: Vec<(&'static str, String)>,
+        file: &'static str,
+        line: u32,
+    ) {
+        if !self.log_enabled(level) {
+            return;
+        }
+        let now = self.clock.now();
+        let (thread_name, thread_id) = current_thread();
+        let time = SystemTime::now();
+        let log = Log {
+            seq: 0,
+            level,
             log: log.into(),
-        });
+            fields,
+            time,
+            monotonic: now,
+            thread_name,
+            thread_id,
+            file,
+            line,
+            count: 1,
+            last_time: time,
+            last_monotonic: now,
+        };
+        self.logs.push(log);
     }
 }
 
-impl<const W: usize, const A: usize> Default for Timer<W, A> {
+#[cfg(not(feature = "disabled"))]
+impl<const W: usize, const A: usize, C: Clock + Default> Default for Timer<W, A, C> {
     fn default() -> Self {
+        Self::with_clock(C::default())
+    }
+}
+
+#[cfg(not(feature = "disabled"))]
+impl<const W: usize, const A: usize, C: Clock> Timer<W, A, C> {
+    /// Construct a timer reading the time from `clock`
+    pub fn with_clock(clock: C) -> Self {
         Self {
             total_count: AtomicUsize::new(0),
-            total_time: AtomicUsize::new(0),
+            total_time: AtomicU64::new(0),
             // Allocate for A elements
-            recent_averages: Mutex::new(vec![0; A]),
-            current_count: AtomicUsize::new(0),
-            current_time: AtomicUsize::new(0),
-            logs: Mutex::new(vec![]),
+            recent_windows: History::default(),
+            current: Windows::default(),
+            window_interval: AtomicU64::new(0),
+            window_start: AtomicU64::new(0),
+            window_closed_at: AtomicU64::new(0),
+            closing: AtomicBool::new(false),
+            flush_after: AtomicU64::new(0),
+            clock,
+            shards: OnceLock::new(),
+            enabled: AtomicBool::new(true),
+            sample_rate: AtomicUsize::new(1),
+            sample_randomly: AtomicBool::new(false),
+            unsampled: AtomicUsize::new(0),
+            log_errors: AtomicBool::new(false),
+            lap_names: LapNames::default(),
+            min_level: AtomicU8::new(LogLevel::default() as u8),
+            logs: LogRing::default(),
         }
     }
 }
+
+/// Nanoseconds from `start` to `end`, or zero if the clock went backwards
+#[cfg(not(feature = "disabled"))]
+fn nanos_between(start: u64, end: u64) -> u64 {
+    end.saturating_sub(start)
+}
+
+/// Name and id of the calling thread, without allocating on every call
+#[cfg(not(feature = "disabled"))]
+fn current_thread() -> (Option<Arc<str>>, ThreadId) {
+    thread_local! {
+        static CURRENT: (Option<Arc<str>>, ThreadId) = {
+            let thread = thread::current();
+            (thread.name().map(Arc::from), thread.id())
+        };
+    }
+    // Threads may still log while their thread-locals are being destroyed
+    CURRENT
+        .try_with(|current| current.clone())
+        .unwrap_or_else(|_| {
+            let thread = thread::current();
+            (thread.name().map(Arc::from), thread.id())
+        })
+}
+
+/// A cheap thread-local pseudo-random number (xorshift64*)
+#[cfg(not(feature = "disabled"))]
+fn random() -> u64 {
+    thread_local! {
+        static STATE: Cell<u64> = Cell::new(RandomState::new().build_hasher().finish() | 1);
+    }
+    STATE.with(|state| {
+        let mut x = state.get();
+        x ^= x >> 12;
+        x ^= x << 25;
+        x ^= x >> 27;
+        state.set(x);
+        x.wrapping_mul(0x2545_f491_4f6c_dd1d)
+    })
+}
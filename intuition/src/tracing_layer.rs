@@ -0,0 +1,259 @@
+//! Timing of [tracing] spans and logging of [tracing] events in the scopes of
+//! a profiler. Requires the `tracing` feature.
+
+use std::{borrow::Cow, fmt::Write};
+
+use tracing::{
+    field::{Field, Visit},
+    span::{Attributes, Id},
+    Event, Level, Subscriber,
+};
+use tracing_subscriber::{layer::Context, registry::LookupSpan, Layer};
+
+use crate::{logs::GLOBAL_SCOPE, profiler::ProfilerExt, timer::LogLevel};
+
+/// A [Layer] that turns [tracing] spans into iterations of the scopes of a
+/// profiler, and events into logs, so that the dashboard shows existing
+/// instrumentation without any [crate::timer::Timer::iteration] calls.
+///
+/// A span is matched to the scope with the same name as the span, or as the
+/// value of a field of the span if set with [TracingLayer::scope_field].
+/// Spans without a matching scope are ignored. Each matched span is one
+/// iteration, from its creation until it is closed; the time it spends
+/// entered is recorded as busy time, like for futures (see
+/// [crate::timer::Timer::instrument]).
+///
+/// Events are logged to the scope of the innermost matched span they occur
+/// in, or else to the fallback scope (see [TracingLayer::fallback]).
+///
+/// ```rust, no_run
+/// use intuition::{construct_profiler, tracing_layer::TracingLayer};
+/// use tracing_subscriber::prelude::*;
+///
+/// construct_profiler!(MyProgramProfiler for MyProgram: global, request, query);
+/// static PROFILER: MyProgramProfiler<10, 10> = MyProgramProfiler::new();
+///
+/// tracing_subscriber::registry()
+///     .with(TracingLayer::from_profiler(&PROFILER))
+///     .init();
+///
+/// let span = tracing::info_span!("request", id = 7);
+/// let _entered = span.enter();
+/// tracing::warn!(retries = 3, "slow query");
+/// ```
+pub struct TracingLayer<P: 'static> {
+    profiler: &'static P,
+    /// Field of a span holding the name of its scope, if not its name
+    scope_field: Option<&'static str>,
+    fallback: String,
+}
+
+/// Timing of a span matched to a scope, kept in the span's extensions
+struct SpanTiming {
+    scope: usize,
+    /// When the span was created
+    start: u64,
+    /// When the span was entered, if it currently is
+    entered_at: u64,
+    /// Number of times the span is currently entered (e.g. on several
+    /// threads at once), of which only the first counts
+    entered: usize,
+    /// Time spent entered so far
+    busy: u64,
+}
+
+impl<P: ProfilerExt + Sync + 'static> TracingLayer<P> {
+    /// Construct a layer feeding a static profiler, like
+    /// [crate::Dash::from_profiler]
+    pub fn from_profiler(p: &'static impl ::core::ops::Deref<Target = P>) -> TracingLayer<P> {
+        TracingLayer::new(p.deref())
+    }
+
+    /// Construct a layer feeding a static profiler that is not behind a
+    /// [Deref](core::ops::Deref), such as a [crate::dynamic::Profiler]
+    pub fn new(profiler: &'static P) -> TracingLayer<P> {
+        TracingLayer {
+            profiler,
+            scope_field: None,
+            fallback: GLOBAL_SCOPE.to_string(),
+        }
+    }
+
+    /// Match spans to scopes by the value of their field called `field`
+    /// (as set when the span is created) instead of by their name, e.g.
+    /// `info_span!("handle", scope = "network")`
+    pub fn scope_field(mut self, field: &'static str) -> Self {
+        self.scope_field = Some(field);
+        self
+    }
+
+    /// Log events outside of any matched span to `scope` instead of
+    /// [GLOBAL_SCOPE]
+    pub fn fallback(mut self, scope: &str) -> Self {
+        self.fallback = scope.to_string();
+        self
+    }
+
+    /// Index of the scope a new span is matched to, if any
+    fn scope(&self, attrs: &Attributes<'_>) -> Option<usize> {
+        match self.scope_field {
+            None => self.profiler.scope_index(attrs.metadata().name()),
+            Some(field) => {
+                let mut visitor = FieldValue { field, value: None };
+                attrs.record(&mut visitor);
+                self.profiler.scope_index(&visitor.value?)
+            }
+        }
+    }
+}
+
+impl<S, P> Layer<S> for TracingLayer<P>
+where
+    S: Subscriber + for<'a> LookupSpan<'a>,
+    P: ProfilerExt + Sync + 'static,
+{
+    fn on_new_span(&self, attrs: &Attributes<'_>, id: &Id, ctx: Context<'_, S>) {
+        let (Some(scope), Some(span)) = (self.scope(attrs), ctx.span(id)) else {
+            return;
+        };
+        let Some(start) = self.profiler.now(scope) else {
+            return;
+        };
+        span.extensions_mut().insert(SpanTiming {
+            scope,
+            start,
+            entered_at: 0,
+            entered: 0,
+            busy: 0,
+        });
+    }
+
+    fn on_enter(&self, id: &Id, ctx: Context<'_, S>) {
+        let Some(span) = ctx.span(id) else {
+            return;
+        };
+        let mut extensions = span.extensions_mut();
+        if let Some(timing) = extensions.get_mut::<SpanTiming>() {
+            if timing.entered == 0 {
+                timing.entered_at = self.profiler.now(timing.scope).unwrap_or(0);
+            }
+            timing.entered += 1;
+        }
+    }
+
+    fn on_exit(&self, id: &Id, ctx: Context<'_, S>) {
+        let Some(span) = ctx.span(id) else {
+            return;
+        };
+        let mut extensions = span.extensions_mut();
+        if let Some(timing) = extensions.get_mut::<SpanTiming>() {
+            timing.entered = timing.entered.saturating_sub(1);
+            if timing.entered == 0 {
+                let now = self.profiler.now(timing.scope).unwrap_or(0);
+                timing.busy = timing
+                    .busy
+                    .saturating_add(now.saturating_sub(timing.entered_at));
+            }
+        }
+    }
+
+    fn on_close(&self, id: Id, ctx: Context<'_, S>) {
+        let Some(span) = ctx.span(&id) else {
+            return;
+        };
+        let timing = span.extensions_mut().remove::<SpanTiming>();
+        if let Some(timing) = timing {
+            if let Some(end) = self.profiler.now(timing.scope) {
+                self.profiler
+                    .record_iteration(timing.scope, timing.start, end, timing.busy);
+            }
+        }
+    }
+
+    fn on_event(&self, event: &Event<'_>, ctx: Context<'_, S>) {
+        let scope = ctx
+            .event_scope(event)
+            .and_then(|spans| {
+                spans
+                    .into_iter()
+                    .find_map(|span| span.extensions().get::<SpanTiming>().map(|t| t.scope))
+            })
+            .or_else(|| self.profiler.scope_index(&self.fallback));
+        let Some(scope) = scope else {
+            return;
+        };
+        let metadata = event.metadata();
+        let level = level(*metadata.level());
+        if !self.profiler.log_enabled(scope, level) {
+            return;
+        }
+
+        let mut fields = Fields::default();
+        event.record(&mut fields);
+        self.profiler.log_fields_at(
+            scope,
+            level,
+            Cow::Owned(fields.message),
+            fields.fields,
+            metadata.file().unwrap_or("<unknown>"),
+            metadata.line().unwrap_or(0),
+        );
+    }
+}
+
+/// Finds the value of one field, as a string
+struct FieldValue {
+    field: &'static str,
+    value: Option<String>,
+}
+
+impl Visit for FieldValue {
+    fn record_str(&mut self, field: &Field, value: &str) {
+        if field.name() == self.field {
+            self.value = Some(value.to_string());
+        }
+    }
+
+    fn record_debug(&mut self, field: &Field, value: &dyn std::fmt::Debug) {
+        if field.name() == self.field {
+            self.value = Some(format!("{value:?}"));
+        }
+    }
+}
+
+/// Collects the message of an event, and its other fields as the structured
+/// fields of its log (see [crate::timer::Log::fields])
+#[derive(Default)]
+struct Fields {
+    message: String,
+    fields: Vec<(&'static str, String)>,
+}
+
+impl Visit for Fields {
+    fn record_str(&mut self, field: &Field, value: &str) {
+        if field.name() == "message" {
+            self.message.push_str(value);
+        } else {
+            self.fields.push((field.name(), value.to_string()));
+        }
+    }
+
+    fn record_debug(&mut self, field: &Field, value: &dyn std::fmt::Debug) {
+        if field.name() == "message" {
+            let _ = write!(self.message, "{value:?}");
+        } else {
+            self.fields.push((field.name(), format!("{value:?}")));
+        }
+    }
+}
+
+/// The [LogLevel] of a [tracing::Level]
+fn level(level: Level) -> LogLevel {
+    match level {
+        Level::ERROR => LogLevel::Error,
+        Level::WARN => LogLevel::Warn,
+        Level::INFO => LogLevel::Info,
+        Level::DEBUG => LogLevel::Debug,
+        _ => LogLevel::Trace,
+    }
+}
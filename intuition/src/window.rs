@@ -0,0 +1,313 @@
+use std::{
+    hint,
+    sync::atomic::{AtomicU64, AtomicUsize, Ordering},
+    thread,
+};
+
+use crate::{
+    histogram::{Histogram, Percentiles},
+    lap::MAX_LAPS,
+    timer::Sample,
+};
+
+/// Accumulates the iterations of (part of) a window.
+pub struct Accumulator {
+    count: AtomicUsize,
+    /// Number of iterations that were timed; see [crate::timer::Sampling]
+    sampled: AtomicUsize,
+    time: AtomicU64,
+    /// Part of `time` not spent running, i.e. `time - busy`. Stored this way
+    /// round (like `nested`) since it is zero for almost every iteration, so
+    /// adding it can usually be skipped.
+    waiting: AtomicU64,
+    /// Part of `time` spent in nested scopes, i.e. `time - self_time`
+    nested: AtomicU64,
+    /// Number of timed iterations that failed; see
+    /// [crate::timer::Timer::iteration_result]
+    failures: AtomicUsize,
+    failure_time: AtomicU64,
+    laps: [AtomicU64; MAX_LAPS],
+    lap_counts: [AtomicUsize; MAX_LAPS],
+    histogram: Histogram,
+}
+
+/// Everything accumulated in a window
+pub(crate) struct Summary {
+    pub(crate) count: usize,
+    pub(crate) sampled: usize,
+    pub(crate) totals: Sample,
+    pub(crate) percentiles: Percentiles,
+}
+
+impl Accumulator {
+    /// Add a sample standing in for `iterations` iterations
+    pub(crate) fn add(&self, sample: &Sample, iterations: usize) {
+        self.histogram.record(sample.time);
+        add_saturating(&self.time, sample.time);
+        let waiting = sample.time.saturating_sub(sample.busy);
+        if waiting > 0 {
+            add_saturating(&self.waiting, waiting);
+        }
+        let nested = sample.time.saturating_sub(sample.self_time);
+        if nested > 0 {
+            add_saturating(&self.nested, nested);
+        }
+        if sample.failures > 0 {
+            self.failures.fetch_add(sample.failures, Ordering::Relaxed);
+            add_saturating(&self.failure_time, sample.failure_time);
+        }
+        for (total, lap) in self.laps.iter().zip(sample.laps) {
+            if lap > 0 {
+                add_saturating(total, lap);
+            }
+        }
+        for (total, count) in self.lap_counts.iter().zip(sample.lap_counts) {
+            if count > 0 {
+                total.fetch_add(count, Ordering::Relaxed);
+            }
+        }
+        self.sampled.fetch_add(1, Ordering::Relaxed);
+        self.count.fetch_add(iterations, Ordering::Relaxed);
+    }
+
+    pub(crate) fn count(&self) -> usize {
+        self.count.load(Ordering::Relaxed)
+    }
+
+    /// Move everything accumulated here into `other`, returning the totals
+    /// that were moved.
+    pub(crate) fn drain_into(&self, other: &Accumulator) -> (usize, Sample) {
+        let count = self.count.swap(0, Ordering::Relaxed);
+        let waiting = self.waiting.swap(0, Ordering::Relaxed);
+        let nested = self.nested.swap(0, Ordering::Relaxed);
+        let totals = self.take_totals();
+        other.count.fetch_add(count, Ordering::Relaxed);
+        other
+            .sampled
+            .fetch_add(self.sampled.swap(0, Ordering::Relaxed), Ordering::Relaxed);
+        add_saturating(&other.time, totals.time);
+        add_saturating(&other.waiting, waiting);
+        add_saturating(&other.nested, nested);
+        other.failures.fetch_add(totals.failures, Ordering::Relaxed);
+        add_saturating(&other.failure_time, totals.failure_time);
+        for (total, lap) in other.laps.iter().zip(totals.laps) {
+            add_saturating(total, lap);
+        }
+        for (total, count) in other.lap_counts.iter().zip(totals.lap_counts) {
+            total.fetch_add(count, Ordering::Relaxed);
+        }
+        self.histogram.drain_into(&other.histogram);
+        (count, totals)
+    }
+
+    /// Reset the accumulator, returning what it accumulated
+    pub(crate) fn take(&self) -> Summary {
+        Summary {
+            count: self.count.swap(0, Ordering::Relaxed),
+            sampled: self.sampled.swap(0, Ordering::Relaxed),
+            totals: self.take_totals(),
+            percentiles: self.histogram.take(),
+        }
+    }
+
+    fn take_totals(&self) -> Sample {
+        let time = self.time.swap(0, Ordering::Relaxed);
+        Sample {
+            time,
+            busy: time.saturating_sub(self.waiting.swap(0, Ordering::Relaxed)),
+            self_time: time.saturating_sub(self.nested.swap(0, Ordering::Relaxed)),
+            failures: self.failures.swap(0, Ordering::Relaxed),
+            failure_time: self.failure_time.swap(0, Ordering::Relaxed),
+            laps: self
+                .laps
+                .each_ref()
+                .map(|lap| lap.swap(0, Ordering::Relaxed)),
+            lap_counts: self
+                .lap_counts
+                .each_ref()
+                .map(|count| count.swap(0, Ordering::Relaxed)),
+        }
+    }
+}
+
+impl Default for Accumulator {
+    fn default() -> Self {
+        Self {
+            count: AtomicUsize::new(0),
+            sampled: AtomicUsize::new(0),
+            time: AtomicU64::new(0),
+            waiting: AtomicU64::new(0),
+            nested: AtomicU64::new(0),
+            failures: AtomicUsize::new(0),
+            failure_time: AtomicU64::new(0),
+            laps: Default::default(),
+            lap_counts: Default::default(),
+            histogram: Histogram::default(),
+        }
+    }
+}
+
+/// The window currently being filled, with linearizable closing.
+///
+/// Windows alternate between two [Accumulator]s. A writer registers itself
+/// with the active one, checks that it is still open, and only then adds to
+/// it. Closing a window flips the active accumulator, marks the old one as
+/// closed and waits for the writers that are still registered with it before
+/// draining it. Registering and closing both modify the same counter, so
+/// either the closer waits for a writer or the writer sees that the window is
+/// closed and moves on to the next one. Every iteration therefore lands in
+/// exactly one window, and windows are drained one at a time, in order.
+///
+/// Writers may also claim a limited number of slots in a window (`W` for
+/// count-based windows). The writer that claims the last slot must close the
+/// window; writers that find it full wait until it has been closed.
+pub struct Windows {
+    buffers: [Buffer; 2],
+    /// Id of the window currently being filled
+    active: AtomicUsize,
+    /// Number of windows that have been fully drained
+    drained: AtomicUsize,
+}
+
+#[derive(Default)]
+struct Buffer {
+    accumulator: Accumulator,
+    /// Iterations claimed in this window so far
+    claimed: AtomicUsize,
+    /// Writers currently registered with this buffer, plus [CLOSED] once its
+    /// window is closing
+    writers: AtomicUsize,
+}
+
+/// Flag of [Buffer::writers] keeping writers out of a window that is closing
+const CLOSED: usize = 1 << (usize::BITS - 1);
+
+/// Where a [Windows::record] ended up
+pub(crate) struct Recorded {
+    /// Id of the window recorded into
+    pub(crate) id: usize,
+    /// Iterations claimed in that window, including these
+    pub(crate) claimed: usize,
+    /// Whether these iterations filled the window, which then has to be closed
+    /// by the caller
+    pub(crate) filled: bool,
+}
+
+impl Windows {
+    /// Record `n` iterations into the active window by calling `f` with its
+    /// accumulator, in a window with room for at most `limit` iterations.
+    pub(crate) fn record(&self, n: usize, limit: usize, f: impl FnOnce(&Accumulator)) -> Recorded {
+        let mut spins = 0;
+        loop {
+            let id = self.active.load(Ordering::Acquire);
+            let buffer = &self.buffers[id % 2];
+            let writers = buffer.writers.fetch_add(1, Ordering::Acquire);
+            // Closing, or even reused for a later window in the meantime
+            if writers & CLOSED != 0 || self.active.load(Ordering::Acquire) != id {
+                buffer.writers.fetch_sub(1, Ordering::Release);
+                backoff(&mut spins);
+                continue;
+            }
+
+            let claimed = buffer.claimed.fetch_add(n, Ordering::Relaxed);
+            if claimed >= limit {
+                // Full, wait for it to be closed
+                buffer.writers.fetch_sub(1, Ordering::Release);
+                backoff(&mut spins);
+                continue;
+            }
+
+            f(&buffer.accumulator);
+            buffer.writers.fetch_sub(1, Ordering::Release);
+            return Recorded {
+                id,
+                claimed: claimed + n,
+                filled: claimed + n >= limit,
+            };
+        }
+    }
+
+    /// Id of the window currently being filled
+    pub(crate) fn active(&self) -> usize {
+        self.active.load(Ordering::Acquire)
+    }
+
+    /// Iterations claimed in the active window so far
+    pub(crate) fn active_claimed(&self) -> usize {
+        self.buffers[self.active() % 2]
+            .claimed
+            .load(Ordering::Acquire)
+    }
+
+    /// Close window `id`, calling `f` with its accumulator once no writer can
+    /// touch it anymore. Returns `None` (without calling `f`) if the window
+    /// was already closed by someone else.
+    pub(crate) fn close<R>(&self, id: usize, f: impl FnOnce(&Accumulator) -> R) -> Option<R> {
+        // The next window reuses the buffer of the previous one, so that one
+        // has to be drained before this one can be closed
+        let mut spins = 0;
+        loop {
+            if self.active.load(Ordering::Acquire) != id {
+                return None;
+            }
+            if self.drained.load(Ordering::Acquire) == id {
+                break;
+            }
+            backoff(&mut spins);
+        }
+        if self
+            .active
+            .compare_exchange(id, id + 1, Ordering::AcqRel, Ordering::Acquire)
+            .is_err()
+        {
+            return None;
+        }
+
+        // Open the next window, whose buffer was drained above. Writers that
+        // already saw it active have been waiting for this.
+        self.buffers[(id + 1) % 2]
+            .writers
+            .fetch_and(!CLOSED, Ordering::Release);
+
+        // Keep writers out of this window and wait for the ones that got in
+        let buffer = &self.buffers[id % 2];
+        buffer.writers.fetch_or(CLOSED, Ordering::AcqRel);
+        while buffer.writers.load(Ordering::Acquire) != CLOSED {
+            backoff(&mut spins);
+        }
+
+        let output = f(&buffer.accumulator);
+        buffer.claimed.store(0, Ordering::Relaxed);
+        self.drained.store(id + 1, Ordering::Release);
+        Some(output)
+    }
+}
+
+impl Default for Windows {
+    fn default() -> Self {
+        Self {
+            buffers: Default::default(),
+            active: AtomicUsize::new(0),
+            drained: AtomicUsize::new(0),
+        }
+    }
+}
+
+/// Add `value` to a time (in nanoseconds), sticking at `u64::MAX` rather than
+/// wrapping around
+pub(crate) fn add_saturating(total: &AtomicU64, value: u64) {
+    let _ = total.fetch_update(Ordering::Relaxed, Ordering::Relaxed, |total| {
+        Some(total.saturating_add(value))
+    });
+}
+
+/// Spin for a bit, then start yielding to whichever thread we are waiting on
+fn backoff(spins: &mut usize) {
+    const MAX_SPINS: usize = 64;
+    if *spins < MAX_SPINS {
+        *spins += 1;
+        hint::spin_loop();
+    } else {
+        thread::yield_now();
+    }
+}
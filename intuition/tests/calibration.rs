@@ -0,0 +1,56 @@
+#![cfg(not(feature = "disabled"))]
+
+mod common;
+
+use std::time::Duration;
+
+use intuition::{
+    calibration,
+    clock::{Clock, MockClock},
+    timer::Timer,
+};
+
+use common::MILLI;
+
+/// A [MockClock] that claims reading it costs a millisecond
+#[derive(Default)]
+struct CostlyClock(MockClock);
+
+impl Clock for CostlyClock {
+    fn now(&self) -> u64 {
+        self.0.now()
+    }
+
+    fn overhead(&self) -> Duration {
+        Duration::from_millis(1)
+    }
+}
+
+// The only test of this binary, as it flips a global switch
+#[test]
+fn overhead_is_subtracted_only_while_switched_on() {
+    let timer: Timer<1, 8, CostlyClock> = Timer::default();
+    let latest = || {
+        let mut latest = 0;
+        timer.recent_windows.read(|window| latest = window.total);
+        latest
+    };
+    let iteration = |micros| {
+        timer.iteration(|| timer.clock.0.advance(Duration::from_micros(micros)));
+    };
+
+    iteration(3000);
+    assert_eq!(latest(), 3 * MILLI);
+
+    calibration::set_subtract_overhead(true);
+    assert!(calibration::subtracts_overhead());
+    iteration(3000);
+    assert_eq!(latest(), 2 * MILLI);
+    // Shorter than the overhead
+    iteration(500);
+    assert_eq!(latest(), 0);
+
+    calibration::set_subtract_overhead(false);
+    iteration(3000);
+    assert_eq!(latest(), 3 * MILLI);
+}
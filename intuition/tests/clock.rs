@@ -0,0 +1,94 @@
+// These check what gets recorded, which is nothing with timing compiled out
+#![cfg(not(feature = "disabled"))]
+
+mod common;
+
+use std::{sync::Arc, time::Duration};
+
+use intuition::{clock::MockClock, timer::Timer};
+
+use common::{windows, MILLI};
+
+#[test]
+fn mock_clock_makes_count_windows_deterministic() {
+    let timer: Timer<4, 8, MockClock> = Timer::default();
+    for _ in 0..2 {
+        for millis in 1..=4 {
+            timer.iteration(|| timer.clock.advance(Duration::from_millis(millis)));
+        }
+    }
+
+    let windows = windows(&timer);
+    assert_eq!(windows.len(), 2);
+    for window in windows {
+        assert_eq!(window.count, 4);
+        assert_eq!(window.total, 10 * MILLI);
+        assert_eq!(window.mean, 10 * MILLI / 4);
+        assert_eq!(window.elapsed, 10 * MILLI);
+        assert_eq!(window.percentiles.min, MILLI);
+        assert_eq!(window.percentiles.max, 4 * MILLI);
+        assert_eq!(window.throughput(), 400);
+    }
+}
+
+#[test]
+fn mock_clock_makes_time_windows_deterministic() {
+    let clock = Arc::new(MockClock::default());
+    let timer: Timer<4, 8, Arc<MockClock>> = Timer::with_clock(clock.clone());
+    timer.set_window_interval(Some(Duration::from_millis(10)));
+
+    // Three iterations in the first window, then nothing for two more
+    for _ in 0..3 {
+        timer.iteration(|| clock.advance(Duration::from_millis(2)));
+    }
+    clock.set(Duration::from_millis(35));
+    timer.close_elapsed_windows();
+
+    let windows = windows(&timer);
+    assert_eq!(windows.len(), 3);
+    assert_eq!(windows[0].count, 3);
+    assert_eq!(windows[0].mean, 2 * MILLI);
+    assert_eq!(windows[0].elapsed, 10 * MILLI);
+    for empty in &windows[1..] {
+        assert_eq!(empty.count, 0);
+        assert_eq!(empty.elapsed, 10 * MILLI);
+    }
+}
+
+#[test]
+fn switching_window_modes_starts_a_fresh_window() {
+    let timer: Timer<4, 8, MockClock> = Timer::default();
+    timer.clock.set(Duration::from_millis(100));
+    // Pending in a count-based window when switching
+    for _ in 0..2 {
+        timer.iteration(|| timer.clock.advance(Duration::from_millis(1)));
+    }
+    timer.set_window_interval(Some(Duration::from_millis(10)));
+    timer.iteration(|| timer.clock.advance(Duration::from_millis(4)));
+    timer.clock.advance(Duration::from_millis(6));
+    timer.close_elapsed_windows();
+
+    let windows = windows(&timer);
+    assert_eq!(windows.len(), 2);
+    assert_eq!((windows[0].count, windows[0].partial), (2, true));
+    assert_eq!(windows[1].count, 1);
+    assert_eq!(windows[1].total, 4 * MILLI);
+    assert_eq!(windows[1].elapsed, 10 * MILLI);
+    assert_eq!(windows[1].throughput(), 100);
+}
+
+#[test]
+fn huge_times_saturate_instead_of_wrapping() {
+    let timer: Timer<4, 8, MockClock> = Timer::default();
+    for _ in 0..4 {
+        timer.iteration(|| timer.clock.advance(Duration::from_nanos(u64::MAX / 3)));
+    }
+
+    assert_eq!(
+        timer.total_time.load(std::sync::atomic::Ordering::Relaxed),
+        u64::MAX
+    );
+    let windows = windows(&timer);
+    assert_eq!(windows[0].total, u64::MAX);
+    assert_eq!(windows[0].percentiles.max, u64::MAX / 3);
+}
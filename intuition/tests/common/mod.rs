@@ -0,0 +1,13 @@
+//! Helpers shared by the integration tests. Not every test uses all of them.
+#![allow(dead_code)]
+
+use intuition::timer::{Timer, WindowStats};
+
+pub const MILLI: u64 = 1_000_000;
+
+/// The windows of the timer's history that have happened, oldest first
+pub fn windows<const W: usize, const A: usize, C>(timer: &Timer<W, A, C>) -> Vec<WindowStats> {
+    let mut windows = vec![];
+    timer.recent_windows.read(|window| windows.push(window));
+    windows.split_off(A.saturating_sub(timer.recent_windows.len()))
+}
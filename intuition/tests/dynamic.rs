@@ -0,0 +1,61 @@
+use std::ptr;
+
+use intuition::{
+    dynamic::Profiler,
+    profiler::{LapBuffer, ProfilerExt},
+    timer::Statistic,
+};
+
+#[test]
+fn scopes_are_registered_once_per_name() {
+    let profiler: Profiler<10, 10> = Profiler::new("Workers");
+    let first = profiler.scope("worker-0");
+    let again = profiler.scope(format!("worker-{}", 0));
+    let other = profiler.scope(String::from("worker-1"));
+
+    assert_eq!(profiler.len(), 2);
+    assert!(ptr::eq(&*first, &*again));
+    assert!(!ptr::eq(&*first, &*other));
+    assert!(ptr::eq(&*profiler.get("worker-1").unwrap(), &*other));
+    assert_eq!(profiler.scope_index("worker-1"), Some(1));
+}
+
+#[test]
+fn buffers_grow_with_scopes_registered_later() {
+    let profiler: Profiler<10, 10> = Profiler::new("Workers");
+    profiler.scope("first");
+
+    // What the dashboard holds when it starts
+    let mut state = profiler.state_buffer();
+    let mut logs = profiler.log_buffer();
+    let mut laps = LapBuffer::new();
+    profiler.update_lap_buffer(&mut laps);
+    assert_eq!(state.len(), 1);
+
+    // Registered while the dashboard is running
+    profiler.scope("second");
+    profiler.scope("third");
+    profiler.update_buffer(&mut state);
+    profiler.update_statistic_buffer(Statistic::Max, &mut state);
+    profiler.update_logs(&mut logs);
+    profiler.update_lap_buffer(&mut laps);
+
+    let names = |names: Vec<&str>| assert_eq!(names, ["first", "second", "third"]);
+    names(state.iter().map(|(name, _)| *name).collect());
+    names(logs.iter().map(|(name, _)| *name).collect());
+    names(laps.iter().map(|(name, _)| *name).collect());
+}
+
+#[cfg(not(feature = "disabled"))]
+#[test]
+fn scopes_share_the_profiler_clock() {
+    use std::{sync::Arc, time::Duration};
+
+    use intuition::clock::{Clock, MockClock};
+
+    let clock = Arc::new(MockClock::default());
+    let profiler: Profiler<10, 10, Arc<MockClock>> = Profiler::with_clock("Workers", clock.clone());
+    clock.advance(Duration::from_millis(5));
+    assert_eq!(profiler.scope("first").clock.now(), 5_000_000);
+    assert!(Arc::ptr_eq(&profiler.scope("second").clock, &clock));
+}
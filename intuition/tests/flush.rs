@@ -0,0 +1,34 @@
+#![cfg(not(feature = "disabled"))]
+
+mod common;
+
+use std::time::Duration;
+
+use intuition::{clock::MockClock, timer::Timer};
+
+use common::{windows, MILLI};
+
+#[test]
+fn stale_windows_are_flushed_as_partial() {
+    let timer: Timer<100, 8, MockClock> = Timer::default();
+    timer.set_flush_after(Some(Duration::from_secs(1)));
+    for _ in 0..3 {
+        timer.iteration(|| timer.clock.advance(Duration::from_millis(100)));
+    }
+
+    // Not stale yet
+    timer.close_elapsed_windows();
+    assert!(windows(&timer).is_empty());
+
+    timer.clock.advance(Duration::from_secs(1));
+    timer.close_elapsed_windows();
+    let windows = windows(&timer);
+    assert_eq!(windows.len(), 1);
+    assert!(windows[0].partial);
+    assert_eq!(windows[0].count, 3);
+    assert_eq!(windows[0].mean, 100 * MILLI);
+
+    // Nothing left to flush
+    timer.flush();
+    assert_eq!(timer.recent_windows.len(), 1);
+}
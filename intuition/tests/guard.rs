@@ -0,0 +1,60 @@
+#![cfg(not(feature = "disabled"))]
+
+mod common;
+
+use std::{
+    sync::{atomic::Ordering, Arc},
+    time::Duration,
+};
+
+use intuition::{clock::MockClock, timer::Timer};
+
+use common::MILLI;
+
+#[test]
+fn finished_guards_record_once() {
+    let timer: Timer<1, 8, MockClock> = Timer::default();
+    let guard = timer.start();
+    timer.clock.advance(Duration::from_millis(3));
+    assert_eq!(guard.finish(), Duration::from_millis(3));
+    // Time after finishing is not part of the iteration
+    timer.clock.advance(Duration::from_millis(1));
+
+    assert_eq!(timer.total_count.load(Ordering::Relaxed), 1);
+    assert_eq!(timer.total_time.load(Ordering::Relaxed), 3 * MILLI);
+    assert_eq!(timer.recent_windows.len(), 1);
+}
+
+#[test]
+fn dropped_guards_record_once() {
+    let timer: Timer<1, 8, MockClock> = Timer::default();
+    {
+        let _guard = timer.start();
+        timer.clock.advance(Duration::from_millis(3));
+    }
+
+    assert_eq!(timer.total_count.load(Ordering::Relaxed), 1);
+    assert_eq!(timer.total_time.load(Ordering::Relaxed), 3 * MILLI);
+}
+
+#[test]
+fn cancelled_guards_record_nothing() {
+    let clock = Arc::new(MockClock::default());
+    let outer: Timer<1, 8, Arc<MockClock>> = Timer::with_clock(clock.clone());
+    let inner: Timer<1, 8, Arc<MockClock>> = Timer::with_clock(clock.clone());
+
+    outer.iteration(|| {
+        let guard = inner.start();
+        clock.advance(Duration::from_millis(3));
+        guard.cancel();
+    });
+
+    assert_eq!(inner.total_count.load(Ordering::Relaxed), 0);
+    assert_eq!(inner.recent_windows.len(), 0);
+    // The cancelled time is the enclosing scope's own
+    let mut windows = vec![];
+    outer.recent_windows.read(|window| windows.push(window));
+    let window = windows.last().unwrap();
+    assert_eq!(window.total, 3 * MILLI);
+    assert_eq!(window.self_time, 3 * MILLI);
+}
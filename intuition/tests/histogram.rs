@@ -0,0 +1,67 @@
+use intuition::histogram::{Histogram, Percentiles};
+
+const MICRO: u64 = 1_000;
+
+fn percentiles(values: impl IntoIterator<Item = u64>) -> Percentiles {
+    let histogram = Histogram::default();
+    values.into_iter().for_each(|value| histogram.record(value));
+    histogram.take()
+}
+
+#[test]
+fn percentiles_are_upper_bounds_of_their_buckets() {
+    let percentiles = percentiles((1..=100).map(|micros| micros * MICRO));
+    assert_eq!(
+        percentiles,
+        Percentiles {
+            min: MICRO,
+            // 50µs lands in [49152, 53247], 90µs in [81920, 90111]
+            p50: 53_247,
+            p90: 90_111,
+            // 99µs lands in [98304, 106495], which is clamped to the max
+            p99: 100 * MICRO,
+            max: 100 * MICRO,
+        }
+    );
+}
+
+#[test]
+fn small_values_are_exact() {
+    let percentiles = percentiles(0..8);
+    assert_eq!((percentiles.min, percentiles.max), (0, 7));
+    assert_eq!(percentiles.p50, 3);
+    assert_eq!(percentiles.p99, 7);
+    // Buckets are one wide up to 16, then two wide
+    assert_eq!(self::percentiles([15, 15, 1000]).p50, 15);
+    assert_eq!(self::percentiles([16, 16, 1000]).p50, 17);
+}
+
+#[test]
+fn percentiles_are_within_an_eighth_of_the_value() {
+    let mut value = 1;
+    while value < u64::MAX / 3 {
+        // The max is far enough out not to clamp the bucket of `value`
+        let p50 = percentiles([value, value, u64::MAX]).p50;
+        assert!(
+            value <= p50 && p50 - value <= value / 8,
+            "{value} reported as {p50}"
+        );
+        value = value * 3 + 1;
+    }
+    assert_eq!(percentiles([u64::MAX]).p50, u64::MAX);
+}
+
+#[test]
+fn take_resets_and_drain_merges() {
+    let histogram = Histogram::default();
+    assert_eq!(histogram.take(), Percentiles::default());
+
+    let other = Histogram::default();
+    histogram.record(10);
+    other.record(20);
+    histogram.drain_into(&other);
+    assert_eq!(histogram.take(), Percentiles::default());
+    let merged = other.take();
+    assert_eq!((merged.min, merged.max), (10, 20));
+    assert_eq!(other.take(), Percentiles::default());
+}
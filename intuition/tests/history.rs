@@ -0,0 +1,117 @@
+use std::{
+    sync::{
+        atomic::{AtomicBool, Ordering},
+        Arc,
+    },
+    thread,
+    time::{Duration, Instant},
+};
+
+use intuition::{histogram::Percentiles, history::History, timer::WindowStats};
+
+/// Window `i`, every field of which is `i`, so that a torn read shows up as
+/// a mix of values
+fn window(i: u64) -> WindowStats {
+    WindowStats {
+        count: i as usize,
+        sampled: i as usize,
+        total: i,
+        busy: i,
+        self_time: i,
+        failures: i as usize,
+        failure_total: i,
+        laps: [i; 8],
+        lap_counts: [i as usize; 8],
+        elapsed: i,
+        mean: i,
+        percentiles: Percentiles {
+            min: i,
+            p50: i,
+            p90: i,
+            p99: i,
+            max: i,
+        },
+        partial: true,
+    }
+}
+
+/// Push consecutive windows into `history` from another thread until the
+/// returned guard is dropped (including by a failed assertion)
+fn spawn_writer<'scope, const A: usize>(
+    s: &'scope thread::Scope<'scope, '_>,
+    history: &'scope History<A>,
+) -> Stop {
+    let stop = Arc::new(AtomicBool::new(false));
+    let stopped = stop.clone();
+    s.spawn(move || {
+        for i in 1.. {
+            history.push(window(i));
+            if stopped.load(Ordering::Relaxed) {
+                break;
+            }
+        }
+    });
+    Stop(stop)
+}
+
+struct Stop(Arc<AtomicBool>);
+
+impl Drop for Stop {
+    fn drop(&mut self) {
+        self.0.store(true, Ordering::Relaxed);
+    }
+}
+
+#[test]
+fn slow_readers_see_whole_windows_or_none() {
+    const A: usize = 4;
+    let history: History<A> = History::default();
+
+    thread::scope(|s| {
+        let _writer = spawn_writer(s, &history);
+        while history.len() < A {
+            thread::yield_now();
+        }
+
+        let mut lapped = 0;
+        for _ in 0..200 {
+            let mut last = 0;
+            history.read(|read| {
+                if read == WindowStats::default() {
+                    // Overwritten (or mid-write) by the time we got to it
+                    lapped += 1;
+                } else {
+                    assert_eq!(read, window(read.total), "torn read");
+                    assert!(read.total > last, "windows out of order");
+                    last = read.total;
+                }
+                // Let the writer lap us
+                thread::sleep(Duration::from_micros(100));
+            });
+        }
+        assert!(lapped > 0, "the writer never lapped the reader");
+    });
+}
+
+#[test]
+fn reads_racing_the_writer_are_never_torn() {
+    let history: History<4> = History::default();
+
+    thread::scope(|s| {
+        let _writer = spawn_writer(s, &history);
+
+        // Read back to back, so that reads get preempted (or run alongside
+        // the writer) mid-copy
+        let mut whole = 0;
+        let started = Instant::now();
+        while started.elapsed() < Duration::from_millis(200) {
+            history.read(|read| {
+                if read != WindowStats::default() {
+                    assert_eq!(read, window(read.total), "torn read");
+                    whole += 1;
+                }
+            });
+        }
+        assert!(whole > 0, "every read was lapped");
+    });
+}
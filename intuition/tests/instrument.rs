@@ -0,0 +1,97 @@
+#![cfg(not(feature = "disabled"))]
+
+use std::{
+    future::Future,
+    pin::pin,
+    sync::Arc,
+    task::{Context, Poll, Wake, Waker},
+    thread,
+    time::Duration,
+};
+
+use intuition::timer::{Statistic, Timer, WindowStats};
+
+/// A minimal executor: poll the future on this thread until it completes,
+/// sleeping `idle` between polls.
+fn block_on<F: Future>(future: F, idle: Duration) -> F::Output {
+    struct NoopWaker;
+    impl Wake for NoopWaker {
+        fn wake(self: Arc<Self>) {}
+    }
+
+    let waker = Waker::from(Arc::new(NoopWaker));
+    let mut cx = Context::from_waker(&waker);
+    let mut future = pin!(future);
+    loop {
+        match future.as_mut().poll(&mut cx) {
+            Poll::Ready(output) => return output,
+            Poll::Pending => thread::sleep(idle),
+        }
+    }
+}
+
+/// A future that is pending `polls` times, spinning for `work` on every poll.
+struct Work {
+    polls: usize,
+    work: Duration,
+}
+
+impl Future for Work {
+    type Output = usize;
+
+    fn poll(mut self: std::pin::Pin<&mut Self>, cx: &mut Context<'_>) -> Poll<usize> {
+        let start = std::time::Instant::now();
+        while start.elapsed() < self.work {}
+        if self.polls == 0 {
+            Poll::Ready(42)
+        } else {
+            self.polls -= 1;
+            cx.waker().wake_by_ref();
+            Poll::Pending
+        }
+    }
+}
+
+fn last_window<const W: usize, const A: usize>(timer: &Timer<W, A>) -> WindowStats {
+    let mut last = WindowStats::default();
+    timer.recent_windows.read(|window| last = window);
+    last
+}
+
+#[test]
+fn instrument_separates_busy_and_waiting_time() {
+    let timer: Timer<1, 4> = Timer::default();
+
+    let output = block_on(
+        timer.instrument(Work {
+            polls: 3,
+            work: Duration::from_millis(2),
+        }),
+        Duration::from_millis(20),
+    );
+    assert_eq!(output, 42);
+
+    let window = last_window(&timer);
+    assert_eq!(window.count, 1);
+    // Four polls of 2ms of work each, with three 20ms sleeps in between
+    assert!(window.busy >= 8_000_000, "{window:?}");
+    assert!(window.total >= window.busy + 60_000_000, "{window:?}");
+    assert!(Statistic::Waiting.of(&window) >= 60_000_000, "{window:?}");
+}
+
+#[test]
+fn instrument_times_from_first_poll() {
+    let timer: Timer<1, 4> = Timer::default();
+
+    // Created long before it is polled; that time shouldn't count
+    let future = timer.instrument(Work {
+        polls: 0,
+        work: Duration::ZERO,
+    });
+    thread::sleep(Duration::from_millis(20));
+    block_on(future, Duration::ZERO);
+
+    let window = last_window(&timer);
+    assert_eq!(window.count, 1);
+    assert!(window.total < 20_000_000, "{window:?}");
+}
@@ -0,0 +1,78 @@
+#![cfg(not(feature = "disabled"))]
+
+mod common;
+
+use std::time::Duration;
+
+use intuition::{clock::MockClock, timer::Timer};
+
+use common::{windows, MILLI};
+
+#[test]
+fn laps_are_summed_per_window() {
+    let timer: Timer<2, 8, MockClock> = Timer::default();
+    for recv in [1, 3] {
+        timer.iteration_laps(|laps| {
+            timer.clock.advance(Duration::from_millis(recv));
+            laps.lap("recv");
+            timer.clock.advance(Duration::from_millis(2));
+            laps.lap("send");
+            // Not part of any lap
+            timer.clock.advance(Duration::from_millis(1));
+        });
+    }
+
+    assert_eq!(
+        timer.lap_names.names().collect::<Vec<_>>(),
+        ["recv", "send"]
+    );
+    let window = windows(&timer)[0];
+    assert_eq!(window.total, 10 * MILLI);
+    assert_eq!(window.laps[..3], [4 * MILLI, 4 * MILLI, 0]);
+    assert_eq!(window.lap_mean(0), 2 * MILLI);
+    assert_eq!(window.lap_mean(1), 2 * MILLI);
+}
+
+#[test]
+fn lap_means_only_count_iterations_that_ended_the_lap() {
+    let timer: Timer<4, 8, MockClock> = Timer::default();
+    for (recv, sent) in [(1, true), (3, false), (2, true), (2, false)] {
+        timer.iteration_laps(|laps| {
+            timer.clock.advance(Duration::from_millis(recv));
+            laps.lap("recv");
+            if sent {
+                timer.clock.advance(Duration::from_millis(4));
+                // Ending a lap twice still counts the iteration once
+                laps.lap("send");
+                laps.lap("send");
+            }
+        });
+    }
+
+    let window = windows(&timer)[0];
+    assert_eq!(window.lap_counts[..3], [4, 2, 0]);
+    assert_eq!(window.lap_mean(0), 2 * MILLI);
+    assert_eq!(window.lap_mean(1), 4 * MILLI);
+    assert_eq!(window.lap_mean(2), 0);
+}
+
+#[test]
+fn lap_buffers_are_reused_between_updates() {
+    let timer: Timer<1, 2, MockClock> = Timer::default();
+    let iterate = |lap| {
+        timer.iteration_laps(|laps| {
+            timer.clock.advance(Duration::from_millis(1));
+            laps.lap(lap);
+        })
+    };
+    let mut buffer = vec![];
+    iterate("first");
+    timer.update_lap_buffer(&mut buffer);
+    let values = buffer[0].1.as_ptr();
+
+    iterate("second");
+    timer.update_lap_buffer(&mut buffer);
+    assert_eq!(buffer[0].1.as_ptr(), values);
+    assert_eq!(buffer[0], ("first", vec![MILLI, 0]));
+    assert_eq!(buffer[1], ("second", vec![0, MILLI]));
+}
@@ -0,0 +1,62 @@
+// Run with `cargo test --features log`
+#![cfg(all(feature = "log", not(feature = "disabled")))]
+
+use intuition::{
+    construct_profiler,
+    log_bridge::LogBridge,
+    timer::{LogLevel, Timer},
+};
+
+construct_profiler!(BridgeProfiler for Bridge: global, network, hasher);
+static PROFILER: BridgeProfiler<10, 10> = BridgeProfiler::new();
+
+/// Levels and messages of the logs of a scope, oldest first
+fn logs<const W: usize, const A: usize>(timer: &Timer<W, A>) -> Vec<(LogLevel, String)> {
+    timer
+        .logs
+        .records()
+        .into_iter()
+        .map(|log| (log.level, log.log.into_owned()))
+        .collect()
+}
+
+// The logger is global, so this is the only test of this file
+#[test]
+fn records_are_routed_to_scopes_by_target() {
+    LogBridge::from_profiler(&PROFILER)
+        .route("hyper", "network")
+        .route("hyper::client::pool", "hasher")
+        .init()
+        .unwrap();
+    PROFILER.network.set_min_level(LogLevel::Debug);
+
+    log::warn!(target: "hyper::client", "connection {} reset", 7);
+    log::debug!(target: "hyper", "polling");
+    log::trace!(target: "hyper", "too verbose");
+    log::error!(target: "hyper::client::pool", "pool exhausted");
+    log::info!(target: "hyperloop", "not hyper");
+    log::debug!("below the fallback's level");
+    let line = line!() + 1;
+    log::info!("no route");
+
+    assert_eq!(
+        logs(&PROFILER.network),
+        [
+            (LogLevel::Warn, "connection 7 reset".to_string()),
+            (LogLevel::Debug, "polling".to_string()),
+        ]
+    );
+    assert_eq!(
+        logs(&PROFILER.hasher),
+        [(LogLevel::Error, "pool exhausted".to_string())]
+    );
+    assert_eq!(
+        logs(&PROFILER.global),
+        [
+            (LogLevel::Info, "not hyper".to_string()),
+            (LogLevel::Info, "no route".to_string()),
+        ]
+    );
+    let last = PROFILER.global.logs.records().pop().unwrap();
+    assert_eq!((last.file, last.line), (file!(), line));
+}
@@ -0,0 +1,271 @@
+// These check what gets logged, which is nothing with logging compiled out
+#![cfg(not(feature = "disabled"))]
+
+mod common;
+
+use std::time::Duration;
+
+use intuition::{
+    clock::MockClock,
+    logs::{LogCursor, RateLimit, RecentLogs},
+    timer::{LogLevel, Timer},
+};
+
+use common::MILLI;
+
+/// Levels of the logs of the timer, oldest first
+fn levels<const W: usize, const A: usize>(timer: &Timer<W, A>) -> Vec<LogLevel> {
+    timer.logs.records().iter().map(|log| log.level).collect()
+}
+
+/// Log every level with a fresh timer whose minimum level is `min_level`
+fn log_every_level(min_level: LogLevel) -> Timer<10, 10> {
+    let timer = Timer::default();
+    timer.set_min_level(min_level);
+    timer.trace("trace");
+    timer.debug("debug");
+    timer.info("info");
+    timer.warn("warn");
+    timer.error("error");
+    timer
+}
+
+#[test]
+fn logs_below_the_min_level_are_dropped() {
+    let timer = log_every_level(LogLevel::default());
+    assert_eq!(
+        levels(&timer),
+        [LogLevel::Info, LogLevel::Warn, LogLevel::Error]
+    );
+
+    let timer = log_every_level(LogLevel::Trace);
+    assert_eq!(levels(&timer), LogLevel::ALL);
+
+    let timer = log_every_level(LogLevel::Error);
+    assert!(!timer.log_enabled(LogLevel::Warn));
+    assert_eq!(levels(&timer), [LogLevel::Error]);
+}
+
+#[test]
+fn levels_raise_and_lower_within_bounds() {
+    assert_eq!(LogLevel::Info.raise(), LogLevel::Warn);
+    assert_eq!(LogLevel::Error.raise(), LogLevel::Error);
+    assert_eq!(LogLevel::Info.lower(), LogLevel::Debug);
+    assert_eq!(LogLevel::Trace.lower(), LogLevel::Trace);
+}
+
+#[test]
+fn readers_follow_the_ring_independently() {
+    let timer: Timer<10, 10> = Timer::default();
+    timer.logs.set_capacity(4);
+    let (mut fast, mut slow) = (LogCursor::default(), LogCursor::default());
+    let read = |cursor: &mut LogCursor| {
+        let mut seqs = vec![];
+        let dropped = timer.logs.read(cursor, |log| seqs.push(log.seq));
+        (seqs, dropped)
+    };
+
+    for i in 0..3 {
+        timer.info(format!("tick {i}"));
+    }
+    assert_eq!(read(&mut fast), (vec![0, 1, 2], 0));
+    assert_eq!(read(&mut fast), (vec![], 0));
+
+    for i in 0..3 {
+        timer.info(format!("tick {i}"));
+    }
+    assert_eq!(timer.logs.len(), 4);
+    assert_eq!(read(&mut fast), (vec![3, 4, 5], 0));
+    // Logs 0 and 1 were dropped before this reader got to them
+    assert_eq!(read(&mut slow), (vec![2, 3, 4, 5], 2));
+    assert_eq!(slow.dropped, 2);
+    assert_eq!(fast.dropped, 0);
+}
+
+#[test]
+fn recent_logs_are_bounded() {
+    let timer: Timer<10, 10> = Timer::default();
+    let mut recent = RecentLogs::new(2);
+    for i in 0..5 {
+        timer.warn(format!("full {i}"));
+        recent.update(&timer.logs);
+    }
+    let seqs: Vec<u64> = recent.logs.iter().map(|log| log.seq).collect();
+    assert_eq!(seqs, [3, 4]);
+    assert_eq!(recent.cursor.dropped, 0);
+}
+
+#[test]
+fn repeated_logs_are_counted_once() {
+    let timer: Timer<10, 10, MockClock> = Timer::default();
+    let mut recent = RecentLogs::default();
+    for _ in 0..3 {
+        timer.warn("gen to sum full");
+        timer.clock.advance(Duration::from_millis(1));
+    }
+    recent.update(&timer.logs);
+    // Logged from another line, so not a repeat
+    timer.warn("gen to sum full");
+    for _ in 0..2 {
+        timer.warn("gen to sum full");
+    }
+    timer.info("done");
+    recent.update(&timer.logs);
+
+    let logs = timer.logs.records();
+    let counts: Vec<u64> = logs.iter().map(|log| log.count).collect();
+    assert_eq!(counts, [3, 1, 2, 1]);
+    assert_eq!((logs[0].monotonic, logs[0].last_monotonic), (0, 2_000_000));
+
+    // The reader caught the first log mid-repeat and saw it again later
+    let seen: Vec<(u64, u64)> = recent.logs.iter().map(|log| (log.seq, log.count)).collect();
+    assert_eq!(seen, [(0, 3), (1, 1), (2, 2), (3, 1)]);
+}
+
+#[test]
+fn readers_see_repeats_of_the_last_log_read() {
+    let timer: Timer<10, 10> = Timer::default();
+    let mut cursor = LogCursor::default();
+    let mut read = || {
+        let mut counts = vec![];
+        timer
+            .logs
+            .read(&mut cursor, |log| counts.push((log.seq, log.count)));
+        counts
+    };
+    let log = || timer.warn("full");
+
+    log();
+    assert_eq!(read(), [(0, 1)]);
+    log();
+    log();
+    assert_eq!(read(), [(0, 3)]);
+    assert_eq!(read(), []);
+}
+
+#[test]
+fn readers_may_log_while_reading() {
+    let timer: Timer<10, 10> = Timer::default();
+    timer.warn("first");
+    let mut cursor = LogCursor::default();
+    let mut read = || {
+        let mut seen = vec![];
+        timer.logs.read(&mut cursor, |log| {
+            seen.push(log.log.clone());
+            timer.info(format!("read {}", log.log));
+        });
+        seen
+    };
+
+    assert_eq!(read(), ["first"]);
+    assert_eq!(read(), ["read first"]);
+    assert_eq!(timer.logs.len(), 3);
+}
+
+#[test]
+fn rate_limit_admits_bursts_then_the_sustained_rate() {
+    let timer: Timer<10, 10, MockClock> = Timer::default();
+    timer.logs.set_rate_limit(Some(RateLimit {
+        per_second: 10,
+        burst: 3,
+    }));
+    let log_five = || {
+        for i in 0..5 {
+            timer.info(format!("log {i}"));
+        }
+    };
+
+    log_five();
+    assert_eq!(timer.logs.len(), 3);
+    assert_eq!(timer.logs.rate_limited(), 2);
+
+    // One more token every 100ms
+    timer.clock.advance(Duration::from_millis(100));
+    log_five();
+    assert_eq!(timer.logs.len(), 4);
+    assert_eq!(timer.logs.rate_limited(), 6);
+
+    timer.logs.set_rate_limit(None);
+    log_five();
+    assert_eq!(timer.logs.rate_limited(), 6);
+}
+
+#[test]
+fn repeats_beyond_the_rate_limit_are_still_counted() {
+    let timer: Timer<10, 10, MockClock> = Timer::default();
+    timer.set_log_rate_limit(Some(RateLimit {
+        per_second: 10,
+        burst: 3,
+    }));
+    for _ in 0..1000 {
+        timer.warn("full");
+    }
+    timer.warn("drained");
+    timer.warn("full");
+
+    let logs = timer.logs.records();
+    let counts: Vec<_> = logs.iter().map(|log| (&*log.log, log.count)).collect();
+    assert_eq!(counts, [("full", 1000), ("drained", 1), ("full", 1)]);
+    assert_eq!(timer.logs.rate_limited(), 0);
+    // Only new logs use up the burst
+    timer.warn("dropped");
+    assert_eq!(timer.logs.rate_limited(), 1);
+}
+
+#[test]
+fn macros_format_only_enabled_logs() {
+    struct Counted<'a>(&'a std::cell::Cell<usize>);
+    impl std::fmt::Display for Counted<'_> {
+        fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+            self.0.set(self.0.get() + 1);
+            f.write_str("counted")
+        }
+    }
+
+    let timer: Timer<10, 10> = Timer::default();
+    let formatted = std::cell::Cell::new(0);
+    let n = 3;
+    intuition::debug!(timer, "{}", Counted(&formatted));
+    intuition::debug!(timer, key = Counted(&formatted), "dropped");
+    assert_eq!(formatted.get(), 0);
+
+    let line = line!() + 1;
+    intuition::warn!(timer, "queue {} full", n);
+    intuition::error!(timer, queue = "jobs", len = n, "queue {n} full");
+    intuition::info!(timer, "static");
+    intuition::log!(timer, LogLevel::Info, "{}", Counted(&formatted));
+    assert_eq!(formatted.get(), 1);
+
+    let logs = timer.logs.records();
+    assert_eq!(logs.len(), 4);
+    assert_eq!(logs[0].level, LogLevel::Warn);
+    assert_eq!(logs[0].log, "queue 3 full");
+    assert_eq!((logs[0].file, logs[0].line), (file!(), line));
+    assert!(logs[0].fields.is_empty());
+    assert_eq!(logs[1].level, LogLevel::Error);
+    assert_eq!(logs[1].log, "queue 3 full");
+    assert_eq!(
+        logs[1].fields,
+        [("queue", "jobs".to_string()), ("len", "3".to_string())]
+    );
+    // Messages without arguments are not copied
+    assert!(matches!(logs[2].log, std::borrow::Cow::Borrowed("static")));
+    assert_eq!(logs[3].log, "counted");
+}
+
+#[test]
+fn logs_record_when_where_and_by_whom() {
+    let timer: Timer<4, 8, MockClock> = Timer::default();
+    timer.clock.set(Duration::from_millis(7));
+    let before = std::time::SystemTime::now();
+    let line = line!() + 1;
+    timer.warn("queue full");
+
+    let logs = timer.logs.records();
+    let log = &logs[0];
+    assert_eq!(log.monotonic, 7 * MILLI);
+    assert!(log.time >= before);
+    assert_eq!((log.file, log.line), (file!(), line));
+    assert_eq!(log.thread_id, std::thread::current().id());
+    assert_eq!(log.thread_name.as_deref(), std::thread::current().name());
+}
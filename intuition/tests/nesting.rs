@@ -0,0 +1,132 @@
+#[cfg(not(feature = "disabled"))]
+mod common;
+
+use intuition::{construct_profiler, profiler::ProfilerExt};
+
+construct_profiler!(Tree: outer { middle { leaf }, sibling }, last);
+
+/// The scopes of a profiler, with their depths, and its title
+fn layout<P: ProfilerExt>(_: &P) -> (&[&str], &[usize], &str) {
+    (P::SCOPES, P::DEPTHS, P::TITLE)
+}
+
+#[test]
+fn nested_declarations_are_flattened_with_their_depth() {
+    let profiler: Profiler<10, 10> = Profiler::new();
+    let (scopes, depths, title) = layout(&*profiler);
+    assert_eq!(scopes, ["outer", "middle", "leaf", "sibling", "last"]);
+    assert_eq!(depths, [0, 1, 2, 1, 0]);
+    assert_eq!(title, "Tree");
+    assert_eq!(profiler.depth(2), 2);
+}
+
+#[cfg(not(feature = "disabled"))]
+mod timing {
+    use std::{panic, sync::Arc, time::Duration};
+
+    use intuition::{
+        clock::MockClock,
+        timer::{Timer, WindowStats},
+    };
+
+    use super::{common::MILLI, Profiler};
+
+    type MockTimer = Timer<1, 8, Arc<MockClock>>;
+
+    /// The latest window of the timer
+    fn latest(timer: &MockTimer) -> WindowStats {
+        let mut latest = WindowStats::default();
+        timer.recent_windows.read(|window| latest = window);
+        latest
+    }
+
+    fn timers<const N: usize>() -> (Arc<MockClock>, [MockTimer; N]) {
+        let clock = Arc::new(MockClock::default());
+        let timers = std::array::from_fn(|_| Timer::with_clock(clock.clone()));
+        (clock, timers)
+    }
+
+    #[test]
+    fn self_time_excludes_nested_iterations() {
+        let (clock, [outer, inner]) = timers();
+        outer.iteration(|| {
+            clock.advance(Duration::from_millis(1));
+            inner.iteration(|| clock.advance(Duration::from_millis(2)));
+            clock.advance(Duration::from_millis(3));
+        });
+
+        let outer = latest(&outer);
+        assert_eq!(outer.total, 6 * MILLI);
+        assert_eq!(outer.self_time, 4 * MILLI);
+        let inner = latest(&inner);
+        assert_eq!(inner.total, 2 * MILLI);
+        assert_eq!(inner.self_time, 2 * MILLI);
+    }
+
+    #[test]
+    fn profilers_share_one_clock_between_scopes() {
+        let clock = Arc::new(MockClock::default());
+        let profiler: Profiler<1, 8, Arc<MockClock>> = Profiler::with_clock(clock.clone());
+        profiler.outer.iteration(|| {
+            clock.advance(Duration::from_millis(1));
+            profiler.middle.iteration(|| {
+                profiler
+                    .leaf
+                    .iteration(|| clock.advance(Duration::from_millis(2)));
+                clock.advance(Duration::from_millis(3));
+            });
+        });
+
+        let (outer, middle, leaf) = (
+            latest(&profiler.outer),
+            latest(&profiler.middle),
+            latest(&profiler.leaf),
+        );
+        assert_eq!((outer.total, outer.self_time), (6 * MILLI, MILLI));
+        assert_eq!((middle.total, middle.self_time), (5 * MILLI, 3 * MILLI));
+        assert_eq!((leaf.total, leaf.self_time), (2 * MILLI, 2 * MILLI));
+
+        // Without a clock given, the scopes still share the one made for them
+        let profiler: Profiler<1, 8, Arc<MockClock>> = Profiler::new();
+        assert!(Arc::ptr_eq(&profiler.outer.clock, &profiler.last.clock));
+    }
+
+    #[test]
+    fn panicking_iterations_leave_no_frame_behind() {
+        let (clock, [outer, inner, leaf]) = timers();
+        outer.iteration(|| {
+            let _ = panic::catch_unwind(|| {
+                inner.iteration(|| {
+                    leaf.iteration(|| clock.advance(Duration::from_millis(2)));
+                    panic!("failed");
+                })
+            });
+            clock.advance(Duration::from_millis(1));
+        });
+
+        // Like a cancelled one, the failed iteration is part of its parent's
+        // own time
+        assert_eq!(inner.recent_windows.len(), 0);
+        let outer = latest(&outer);
+        assert_eq!(outer.total, 3 * MILLI);
+        assert_eq!(outer.self_time, 3 * MILLI);
+    }
+
+    #[test]
+    fn guards_ending_out_of_order_only_end_their_own_iteration() {
+        let (clock, [first, second]) = timers();
+        let first_guard = first.start();
+        let second_guard = second.start();
+        clock.advance(Duration::from_millis(1));
+        drop(first_guard);
+        clock.advance(Duration::from_millis(2));
+        drop(second_guard);
+
+        let first = latest(&first);
+        assert_eq!(first.total, MILLI);
+        assert_eq!(first.self_time, MILLI);
+        let second = latest(&second);
+        assert_eq!(second.total, 3 * MILLI);
+        assert_eq!(second.self_time, 3 * MILLI);
+    }
+}
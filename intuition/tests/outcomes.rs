@@ -0,0 +1,39 @@
+#![cfg(not(feature = "disabled"))]
+
+mod common;
+
+use std::time::Duration;
+
+use intuition::{
+    clock::MockClock,
+    timer::{Statistic, Timer},
+};
+
+use common::{windows, MILLI};
+
+#[test]
+fn outcomes_are_timed_separately() {
+    let timer: Timer<4, 8, MockClock> = Timer::default();
+    timer.set_log_errors(true);
+    for (millis, ok) in [(1, true), (1, true), (1, true), (5, false)] {
+        let _ = timer.iteration_result(|| {
+            timer.clock.advance(Duration::from_millis(millis));
+            if ok {
+                Ok(())
+            } else {
+                Err("timed out")
+            }
+        });
+    }
+
+    let window = windows(&timer)[0];
+    assert_eq!(window.failures, 1);
+    assert_eq!(Statistic::ErrorRate.of(&window), 250);
+    assert_eq!(Statistic::OkMean.of(&window), MILLI);
+    assert_eq!(Statistic::ErrMean.of(&window), 5 * MILLI);
+    assert_eq!(window.mean, 2 * MILLI);
+
+    let logs = timer.logs.records();
+    assert_eq!(logs.len(), 1);
+    assert_eq!(logs[0].log, "timed out");
+}
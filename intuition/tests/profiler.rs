@@ -0,0 +1,58 @@
+use intuition::{
+    construct_profiler,
+    profiler::{LogBuffer, ProfilerExt, StateBuffer},
+    timer::{LogLevel, Sampling},
+    Dash,
+};
+
+/// A profiler implementing only what [ProfilerExt] originally required
+struct Minimal;
+
+impl ProfilerExt for Minimal {
+    const SCOPES: &'static [&'static str] = &["only"];
+    const TITLE: &'static str = "Minimal";
+    const WINDOW_SIZE: usize = 10;
+    const NUM_AVERAGES: usize = 10;
+
+    fn update_logs(&self, _buffer: &mut LogBuffer) {}
+
+    fn update_buffer(&self, _buffer: &mut StateBuffer) {}
+
+    fn state_buffer(&self) -> StateBuffer {
+        vec![("only", Vec::with_capacity(10))]
+    }
+
+    fn log_buffer(&self) -> LogBuffer {
+        vec![("only", Default::default())]
+    }
+}
+
+static MINIMAL: Minimal = Minimal;
+
+#[test]
+fn minimal_profilers_still_get_a_dash() {
+    let _dash = Dash::new(&MINIMAL);
+    assert_eq!(MINIMAL.depth(0), 0);
+    assert_eq!(MINIMAL.sampling(0), Sampling::All);
+    assert!(!MINIMAL.log_enabled(0, LogLevel::Error));
+    assert_eq!(MINIMAL.now(0), None);
+    let mut buffer = MINIMAL.state_buffer();
+    MINIMAL.update_throughput_buffer(&mut buffer);
+    assert!(buffer[0].1.is_empty());
+}
+
+construct_profiler!(Three: a, b, c);
+
+#[test]
+fn scopes_are_found_by_index() {
+    let profiler: Profiler<10, 10> = Profiler::new();
+    profiler.set_min_level(1, LogLevel::Error);
+    // Levels are not kept with logging compiled out
+    if !cfg!(feature = "disabled") {
+        assert_eq!(profiler.b.min_level(), LogLevel::Error);
+    }
+    assert_eq!(profiler.min_level(2), LogLevel::Info);
+    assert!(profiler.scope(2).is_some());
+    assert!(profiler.scope(3).is_none());
+    assert_eq!(profiler.scope_index("c"), Some(2));
+}
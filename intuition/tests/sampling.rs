@@ -0,0 +1,52 @@
+#![cfg(not(feature = "disabled"))]
+
+mod common;
+
+use std::{sync::atomic::Ordering, time::Duration};
+
+use intuition::{
+    clock::MockClock,
+    timer::{Sampling, Timer},
+};
+
+use common::{windows, MILLI};
+
+#[test]
+fn sampled_windows_count_every_iteration() {
+    let timer: Timer<100, 8, MockClock> = Timer::default();
+    timer.set_sampling(Sampling::Every(10));
+    for _ in 0..200 {
+        timer.iteration(|| timer.clock.advance(Duration::from_millis(1)));
+    }
+
+    let windows = windows(&timer);
+    assert_eq!(windows.len(), 2);
+    for window in windows {
+        assert_eq!(window.count, 100);
+        assert_eq!(window.sampled, 10);
+        assert_eq!(window.mean, MILLI);
+    }
+}
+
+#[test]
+fn random_sampling_times_about_one_in_n() {
+    let timer: Timer<1000, 200, MockClock> = Timer::default();
+    timer.set_sampling(Sampling::Random(10));
+    assert_eq!(timer.sampling(), Sampling::Random(10));
+    for _ in 0..100_000 {
+        timer.iteration(|| timer.clock.advance(Duration::from_micros(1)));
+    }
+    timer.flush();
+
+    assert_eq!(timer.total_count(), 100_000);
+    let windows = windows(&timer);
+    let count: usize = windows.iter().map(|window| window.count).sum();
+    let sampled: usize = windows.iter().map(|window| window.sampled).sum();
+    // Apart from those since the last timed iteration
+    assert_eq!(count, 100_000 - timer.unsampled.load(Ordering::Relaxed));
+    // Within 10 standard deviations of the expected 10 000
+    assert!((9_000..=11_000).contains(&sampled), "{sampled} timed");
+    for window in windows.iter().filter(|window| window.sampled > 0) {
+        assert_eq!(window.mean, MILLI / 1000);
+    }
+}
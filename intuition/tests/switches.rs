@@ -0,0 +1,66 @@
+use intuition::timer::Timer;
+
+#[cfg(not(feature = "disabled"))]
+#[test]
+fn switched_off_scopes_record_nothing() {
+    use std::sync::atomic::Ordering;
+
+    use intuition::timer;
+
+    let timer: Timer<10, 10> = Timer::default();
+    let iterate = || {
+        timer.iteration(|| ());
+        let _ = timer.iteration_result(|| Err::<(), _>("failed"));
+        timer.iteration_laps(|laps| laps.lap("only"));
+        timer.start().finish();
+    };
+
+    timer.set_enabled(false);
+    iterate();
+    assert_eq!(timer.total_count.load(Ordering::Relaxed), 0);
+
+    timer.set_enabled(true);
+    timer::set_enabled(false);
+    iterate();
+    assert_eq!(timer.total_count.load(Ordering::Relaxed), 0);
+
+    timer::set_enabled(true);
+    iterate();
+    assert_eq!(timer.total_count.load(Ordering::Relaxed), 4);
+}
+
+#[cfg(feature = "disabled")]
+mod passthrough {
+    use std::{mem::size_of_val, time::Duration};
+
+    use intuition::{construct_profiler, profiler::ProfilerExt};
+
+    use super::*;
+
+    construct_profiler!(Switches: only);
+
+    #[test]
+    fn timers_are_zero_sized_passthroughs() {
+        let timer: Timer<10, 10> = Timer::default();
+        assert_eq!(size_of_val(&timer), 0);
+        assert_eq!(size_of_val(&timer.start()), 0);
+        assert!(!timer.is_enabled());
+
+        assert_eq!(timer.iteration(|| 7), 7);
+        let laps = timer.iteration_laps(|laps| {
+            laps.lap("first");
+            laps.lap("second");
+            2
+        });
+        assert_eq!(laps, 2);
+        assert_eq!(timer.iteration_result(|| "7".parse::<u8>()), Ok(7));
+        assert_eq!(timer.start().finish(), Duration::ZERO);
+    }
+
+    #[test]
+    fn profilers_have_nothing_to_calibrate() {
+        let profiler: Profiler<10, 10> = Profiler::new();
+        assert_eq!(profiler.only.iteration(|| 1 + 1), 2);
+        assert_eq!(profiler.overhead(), Duration::ZERO);
+    }
+}
@@ -0,0 +1,75 @@
+// Run with `cargo test --features tracing`
+#![cfg(all(feature = "tracing", not(feature = "disabled")))]
+
+mod common;
+
+use std::{sync::Arc, time::Duration};
+
+use intuition::{
+    clock::MockClock,
+    construct_profiler,
+    timer::{LogLevel, Timer},
+    tracing_layer::TracingLayer,
+};
+use tracing_subscriber::prelude::*;
+
+use common::{windows, MILLI};
+
+construct_profiler!(TracedProfiler for Traced: global, request, query);
+// Every span closes a window
+static PROFILER: TracedProfiler<1, 10, Arc<MockClock>> = TracedProfiler::new();
+
+fn logs<const W: usize, const A: usize>(
+    timer: &Timer<W, A, Arc<MockClock>>,
+) -> Vec<(LogLevel, String)> {
+    timer
+        .logs
+        .records()
+        .into_iter()
+        .map(|log| (log.level, log.log.into_owned()))
+        .collect()
+}
+
+#[test]
+fn spans_are_iterations_and_events_are_logs() {
+    let subscriber = tracing_subscriber::registry()
+        .with(TracingLayer::from_profiler(&PROFILER).scope_field("scope"));
+    let clock = &PROFILER.request.clock;
+    tracing::subscriber::with_default(subscriber, || {
+        let span = tracing::info_span!("request", scope = "request", id = 7);
+        clock.advance(Duration::from_millis(1));
+        span.in_scope(|| {
+            clock.advance(Duration::from_millis(2));
+            tracing::warn!(retries = 3, table = "users", "slow query");
+        });
+        clock.advance(Duration::from_millis(3));
+        drop(span);
+
+        // Matched by field, not by name
+        tracing::info_span!("handle", scope = "query").in_scope(|| {});
+        tracing::info_span!("query").in_scope(|| {});
+        tracing::info!("outside of any span");
+        tracing::debug!("below the fallback's level");
+    });
+
+    let request = windows(&PROFILER.request);
+    assert_eq!(request.len(), 1);
+    assert_eq!(request[0].total, 6 * MILLI);
+    assert_eq!(request[0].busy, 2 * MILLI);
+    assert_eq!(windows(&PROFILER.query).len(), 1);
+
+    assert_eq!(
+        logs(&PROFILER.request),
+        [(LogLevel::Warn, "slow query".to_string())]
+    );
+    // Fields are kept apart from the message
+    assert_eq!(
+        PROFILER.request.logs.records()[0].fields,
+        [("retries", "3".to_string()), ("table", "users".to_string())]
+    );
+    assert_eq!(
+        logs(&PROFILER.global),
+        [(LogLevel::Info, "outside of any span".to_string())]
+    );
+    assert!(PROFILER.global.logs.records()[0].fields.is_empty());
+}
@@ -0,0 +1,30 @@
+// Run with `cargo test --features tsc`
+#![cfg(all(feature = "tsc", target_arch = "x86_64"))]
+
+use std::{
+    thread,
+    time::{Duration, Instant},
+};
+
+use intuition::clock::{Clock, TscClock};
+
+#[test]
+fn tsc_clock_is_monotonic_and_follows_the_std_clock() {
+    let clock = TscClock::default();
+    let (start, instant) = (clock.now(), Instant::now());
+    let mut last = start;
+    for _ in 0..10_000 {
+        let now = clock.now();
+        assert!(now >= last, "went back from {last} to {now}");
+        last = now;
+    }
+
+    thread::sleep(Duration::from_millis(50));
+    let tsc = clock.now() - start;
+    let std = instant.elapsed().as_nanos() as u64;
+    // Within 10% of each other, allowing for the reads not being simultaneous
+    assert!(
+        tsc.abs_diff(std) < std / 10,
+        "{tsc} ns by the TSC, {std} ns by Instant"
+    );
+}
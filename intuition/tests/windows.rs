@@ -0,0 +1,182 @@
+#![cfg(not(feature = "disabled"))]
+
+mod common;
+
+use std::{
+    cell::Cell,
+    sync::{atomic::Ordering, Arc, Barrier},
+    thread,
+    time::Duration,
+};
+
+use intuition::{
+    clock::{Clock, MockClock},
+    timer::Timer,
+};
+
+use common::{windows, MILLI};
+
+const THREADS: usize = 8;
+const ITERATIONS: usize = 20_000;
+const WINDOW: usize = 100;
+// Large enough to hold every window of the test
+const HISTORY: usize = THREADS * ITERATIONS / WINDOW;
+
+/// Hammer `timer` from many threads at once
+fn hammer(timer: &'static Timer<WINDOW, HISTORY>) {
+    let start = Arc::new(Barrier::new(THREADS));
+    let handles: Vec<_> = (0..THREADS)
+        .map(|_| {
+            let start = start.clone();
+            thread::spawn(move || {
+                start.wait();
+                for i in 0..ITERATIONS {
+                    timer.iteration(|| std::hint::black_box(i));
+                }
+            })
+        })
+        .collect();
+    for handle in handles {
+        handle.join().unwrap();
+    }
+}
+
+#[test]
+fn concurrent_windows_account_for_every_iteration() {
+    let timer: &'static Timer<WINDOW, HISTORY> = Box::leak(Box::default());
+    hammer(timer);
+
+    let windows = windows(timer);
+    assert_eq!(timer.recent_windows.len(), HISTORY);
+    // Every window closed with exactly W iterations...
+    assert!(windows.iter().all(|window| window.count == WINDOW));
+    // ...and together they hold exactly every iteration and nanosecond
+    let total_time: u64 = windows.iter().map(|window| window.total).sum();
+    assert_eq!(
+        timer.total_count.load(Ordering::Acquire),
+        THREADS * ITERATIONS
+    );
+    assert_eq!(total_time, timer.total_time.load(Ordering::Acquire));
+}
+
+#[test]
+fn concurrent_sharded_windows_account_for_every_iteration() {
+    let timer: &'static Timer<WINDOW, HISTORY> = Box::leak(Box::default());
+    timer.enable_sharding(THREADS);
+    hammer(timer);
+    timer.close_elapsed_windows();
+
+    let windows = windows(timer);
+    let count: usize = windows.iter().map(|window| window.count).sum();
+    let total_time: u64 = windows.iter().map(|window| window.total).sum();
+    assert_eq!(count, THREADS * ITERATIONS);
+    assert_eq!(count, timer.total_count.load(Ordering::Acquire));
+    assert_eq!(total_time, timer.total_time.load(Ordering::Acquire));
+}
+
+thread_local! {
+    static HELD: Cell<bool> = const { Cell::new(false) };
+}
+
+/// A [MockClock] which can hold a thread's next reading until released, to
+/// pin down where that thread's iteration ends relative to other threads
+struct GatedClock {
+    time: MockClock,
+    gate: Barrier,
+}
+
+impl GatedClock {
+    fn new() -> Arc<Self> {
+        Arc::new(Self {
+            time: MockClock::default(),
+            gate: Barrier::new(2),
+        })
+    }
+
+    /// Hold the calling thread's next reading of the clock until released
+    fn hold_next_reading(&self) {
+        HELD.with(|held| held.set(true));
+    }
+
+    /// Wait for the held thread to read the clock, run `f`, then let that
+    /// reading through
+    fn release(&self, f: impl FnOnce()) {
+        self.gate.wait();
+        f();
+        self.gate.wait();
+    }
+}
+
+impl Clock for GatedClock {
+    fn now(&self) -> u64 {
+        if HELD.with(|held| held.replace(false)) {
+            self.gate.wait();
+            self.gate.wait();
+        }
+        self.time.now()
+    }
+
+    fn overhead(&self) -> Duration {
+        Duration::ZERO
+    }
+}
+
+#[test]
+fn iterations_ending_after_a_close_go_into_the_next_window() {
+    let clock = GatedClock::new();
+    let timer: Timer<2, 8, Arc<GatedClock>> = Timer::with_clock(clock.clone());
+    let tick = |millis| timer.iteration(|| clock.time.advance(Duration::from_millis(millis)));
+
+    thread::scope(|s| {
+        // Starts before the first window closes, but ends after
+        let slow = s.spawn(|| timer.iteration(|| clock.hold_next_reading()));
+        clock.release(|| {
+            tick(1);
+            tick(2);
+        });
+        slow.join().unwrap();
+    });
+    tick(4);
+
+    let windows = windows(&timer);
+    assert_eq!(windows.len(), 2);
+    assert_eq!((windows[0].count, windows[0].total), (2, 3 * MILLI));
+    assert_eq!((windows[1].count, windows[1].total), (2, 7 * MILLI));
+    assert_eq!(timer.total_count.load(Ordering::Acquire), 4);
+    assert_eq!(timer.total_time.load(Ordering::Acquire), 10 * MILLI);
+}
+
+#[test]
+fn time_windows_close_in_order_around_a_slow_iteration() {
+    let clock = GatedClock::new();
+    let timer: Timer<100, 8, Arc<GatedClock>> = Timer::with_clock(clock.clone());
+    timer.set_window_interval(Some(Duration::from_millis(10)));
+    let tick = |millis| timer.iteration(|| clock.time.advance(Duration::from_millis(millis)));
+
+    tick(2);
+    thread::scope(|s| {
+        // Starts in the first window and ends in the third
+        let slow = s.spawn(|| timer.iteration(|| clock.hold_next_reading()));
+        clock.release(|| {
+            clock.time.set(Duration::from_millis(12));
+            // Closes the first window
+            tick(1);
+            clock.time.set(Duration::from_millis(25));
+            // Closes the second
+            timer.close_elapsed_windows();
+        });
+        slow.join().unwrap();
+    });
+    clock.time.set(Duration::from_millis(30));
+    timer.close_elapsed_windows();
+
+    let windows = windows(&timer);
+    assert_eq!(windows.len(), 3);
+    let counts: Vec<_> = windows.iter().map(|window| window.count).collect();
+    let totals: Vec<_> = windows.iter().map(|window| window.total).collect();
+    let elapsed: Vec<_> = windows.iter().map(|window| window.elapsed).collect();
+    assert_eq!(counts, [1, 1, 1]);
+    assert_eq!(totals, [2 * MILLI, MILLI, 23 * MILLI]);
+    assert_eq!(elapsed, [10 * MILLI; 3]);
+    assert_eq!(timer.total_time.load(Ordering::Acquire), 26 * MILLI);
+}